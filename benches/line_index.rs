@@ -0,0 +1,74 @@
+#![feature(test)]
+
+extern crate boyer_moore;
+extern crate test;
+
+use boyer_moore::search::lines::LineIndex;
+use boyer_moore::search::tree::Tree;
+use test::Bencher;
+
+/// A log of a hundred thousand short lines.
+fn sample_log() -> String {
+    let mut text = String::new();
+    for i in 0..100000 {
+        text.push_str(&format!("2024-01-01 12:00:{:02} INFO request {} served\n", i % 60, i));
+    }
+    return text;
+}
+
+/// The index as it used to be built, one boxed tree node per line.
+fn build_line_tree(text: &str) -> Tree<usize, usize> {
+    let mut tree = Tree::new();
+    let mut line_count = 1;
+
+    tree.insert(0, line_count);
+    line_count += 1;
+
+    for (offset, byte) in text.bytes().enumerate() {
+        if byte == b'\n' {
+            tree.insert(offset, line_count);
+            line_count += 1;
+        }
+    }
+    return tree;
+}
+
+#[bench]
+fn build_line_index(b: &mut Bencher) {
+    let text = sample_log();
+    b.bytes = text.len() as u64;
+    b.iter(|| LineIndex::new(&text));
+}
+
+#[bench]
+fn build_tree_index(b: &mut Bencher) {
+    let text = sample_log();
+    b.bytes = text.len() as u64;
+    b.iter(|| build_line_tree(&text));
+}
+
+#[bench]
+fn lookup_line_index(b: &mut Bencher) {
+    let text = sample_log();
+    let index = LineIndex::new(&text);
+    b.iter(|| {
+        let mut total = 0;
+        for offset in (0..text.len()).step_by(997) {
+            total += index.line(offset);
+        }
+        return total;
+    });
+}
+
+#[bench]
+fn lookup_tree_index(b: &mut Bencher) {
+    let text = sample_log();
+    let tree = build_line_tree(&text);
+    b.iter(|| {
+        let mut total = 0;
+        for offset in (0..text.len()).step_by(997) {
+            total += *tree.lower_bound(&offset).unwrap().1;
+        }
+        return total;
+    });
+}
@@ -0,0 +1,899 @@
+//!
+//! Command line handling for the `search` binary.
+//!
+//! The arguments are parsed into a `Config` which is then handed to `run`,
+//! so everything except the process entry point can be tested directly.
+//!
+//! ```
+//! use boyer_moore::cli::{self, Command};
+//!
+//! let args = vec!["--".to_string(), "-pattern".to_string(), "input.txt".to_string()];
+//! match cli::parse(args) {
+//!     Ok(Command::Search(config)) => assert_eq!("-pattern", config.pattern),
+//!     _ => panic!("expected a search"),
+//! }
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+
+use search::edit::EditMatch;
+use search::edit::EditSearcher;
+use search::fold::Folding;
+use search::fold::FoldingSearcher;
+use search::grep;
+use search::lines::LazyLineIndex;
+use search::lines::LineIndex;
+use search::mismatch::MismatchSearcher;
+use search::normalize::Form;
+use search::normalize::NormalizingSearcher;
+use search::search;
+use search::searcher::Searcher;
+use search::wildcard::WildcardSearcher;
+
+/// The version reported by `--version`.
+pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// Everything the `search` binary needs to know to perform a run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// the string to search for
+    pub pattern: String,
+    /// the files to search in, in the order they were given
+    pub files: Vec<String>,
+    /// when set, every match is replaced with this text
+    pub replace: Option<String>,
+    /// write replacements back to the files instead of printing them
+    pub in_place: bool,
+    /// keep a copy of each file modified in place under this suffix
+    pub backup_suffix: Option<String>,
+    /// `?` in the pattern matches any single character
+    pub wildcard: bool,
+    /// when set, report places that differ from the pattern in up to this
+    /// many bytes
+    pub max_mismatches: Option<usize>,
+    /// when set, report places within this many inserted, deleted or
+    /// substituted bytes of the pattern
+    pub max_edits: Option<usize>,
+    /// only matches that are whole words count
+    pub whole_word: bool,
+    /// only matches that are whole lines count
+    pub whole_line: bool,
+    /// print the lines without a match instead of the ones with one
+    pub invert: bool,
+    /// print only the number of selected lines
+    pub count: bool,
+    /// the number of lines to print ahead of each selected line
+    pub before_context: Option<usize>,
+    /// the number of lines to print behind each selected line
+    pub after_context: Option<usize>,
+    /// the number of lines to print around each selected line, unless
+    /// `before_context` or `after_context` say otherwise
+    pub context: Option<usize>,
+    /// when set, the pattern and the files are compared in this Unicode
+    /// normalization form
+    pub normalize: Option<Form>,
+    /// ignore accents and other combining marks and fold full-width and
+    /// half-width forms when comparing
+    pub fold: bool,
+    /// with `fold`, also split typographic ligatures such as "ﬁ"
+    pub fold_ligatures: bool,
+}
+
+impl Config {
+
+    ///
+    /// Whether the files are searched line by line, printing each selected
+    /// line once rather than every match.
+    ///
+    pub fn is_line_oriented(&self) -> bool {
+        return self.invert || self.count || self.before_context.is_some()
+            || self.after_context.is_some() || self.context.is_some();
+    }
+}
+
+/// What the command line asked the binary to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Search(Config),
+    Help,
+    Version,
+}
+
+/// The reasons a command line can be rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliError {
+    /// an option that the binary does not know about
+    UnknownOption(String),
+    /// an option that requires a value was given without one
+    MissingValue(String),
+    /// an option was given a value that it does not take
+    UnexpectedValue(String),
+    /// an option was given a value that it cannot use
+    InvalidValue(String, String),
+    /// a required positional argument was not given
+    MissingArgument(&'static str),
+    /// the first option only makes sense together with the second
+    Requires(&'static str, &'static str),
+    /// the two options cannot be used together
+    Conflicts(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::UnknownOption(ref option) =>
+                write!(f, "unrecognized option '{}'", option),
+            CliError::MissingValue(ref option) =>
+                write!(f, "option '{}' requires a value", option),
+            CliError::UnexpectedValue(ref option) =>
+                write!(f, "option '{}' does not take a value", option),
+            CliError::InvalidValue(ref option, ref value) =>
+                write!(f, "invalid value '{}' for option '{}'", value, option),
+            CliError::MissingArgument(name) =>
+                write!(f, "missing required argument [{}]", name),
+            CliError::Requires(option, required) =>
+                write!(f, "option '{}' requires '{}'", option, required),
+            CliError::Conflicts(option, other) =>
+                write!(f, "option '{}' cannot be used with '{}'", option, other),
+        }
+    }
+}
+
+///
+/// Returns the help text for the binary.
+/// # Arguments
+/// * `program` - the name the binary was invoked as
+///
+pub fn usage(program: &str) -> String {
+    let mut text = String::new();
+    text.push_str(&format!("Usage: {} [OPTIONS] [--] PATTERN FILE...\n", program));
+    text.push_str("Search for PATTERN in each FILE using the Boyer-Moore algorithm.\n");
+    text.push_str("\n");
+    text.push_str("Options:\n");
+    text.push_str("  -r, --replace TEXT          print the files with every match replaced by TEXT\n");
+    text.push_str("      --in-place              write the replacements back to the files\n");
+    text.push_str("      --backup-suffix SUFFIX  with --in-place, keep the original as FILE+SUFFIX\n");
+    text.push_str("      --wildcard              '?' in PATTERN matches any character, '\\?' a\n");
+    text.push_str("                              literal '?'\n");
+    text.push_str("      --max-mismatches K      also report matches that differ in up to K bytes\n");
+    text.push_str("      --max-edits K           also report matches within K inserted, deleted or\n");
+    text.push_str("                              substituted bytes\n");
+    text.push_str("  -w, --word-regexp           only match PATTERN as a whole word\n");
+    text.push_str("  -x, --line-regexp           only match PATTERN as a whole line\n");
+    text.push_str("      --normalize FORM        compare PATTERN and FILE in normalization FORM,\n");
+    text.push_str("                              'nfc' or 'nfkc'\n");
+    text.push_str("      --fold                  ignore accents and full-width or half-width forms\n");
+    text.push_str("      --fold-ligatures        with --fold, also split ligatures such as 'ﬁ'\n");
+    text.push_str("  -v, --invert-match          print the lines that do not contain PATTERN\n");
+    text.push_str("  -c, --count                 print only the number of selected lines\n");
+    text.push_str("  -A, --after-context NUM     print NUM lines after each selected line\n");
+    text.push_str("  -B, --before-context NUM    print NUM lines before each selected line\n");
+    text.push_str("  -C, --context NUM           print NUM lines around each selected line\n");
+    text.push_str("  -h, --help                  print this help and exit\n");
+    text.push_str("  -V, --version               print the version and exit\n");
+    text.push_str("  --                          treat every following argument as positional\n");
+    return text;
+}
+
+///
+/// Parses the command line arguments, not including the program name.
+/// # Arguments
+/// * `args` - the arguments that were passed to the binary
+///
+/// # Result
+/// The command to run, or the reason the arguments were rejected
+///
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, CliError> {
+    let mut config = Config::default();
+    let mut positional: Vec<String> = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args);
+            break;
+        } else if arg.starts_with("--") {
+            let (name, value) = match arg.find('=') {
+                Some(index) => (arg[2..index].to_string(), Some(arg[(index + 1)..].to_string())),
+                None => (arg[2..].to_string(), None),
+            };
+            let flag = format!("--{}", name);
+            match name.as_str() {
+                "help" => {
+                    no_value(&flag, value)?;
+                    return Ok(Command::Help);
+                },
+                "version" => {
+                    no_value(&flag, value)?;
+                    return Ok(Command::Version);
+                },
+                "replace" => config.replace = Some(take_value(&flag, value, &mut args)?),
+                "in-place" => {
+                    no_value(&flag, value)?;
+                    config.in_place = true;
+                },
+                "backup-suffix" => config.backup_suffix = Some(take_value(&flag, value, &mut args)?),
+                "wildcard" => {
+                    no_value(&flag, value)?;
+                    config.wildcard = true;
+                },
+                "max-mismatches" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.max_mismatches = Some(parse_count(flag, value)?);
+                },
+                "word-regexp" => {
+                    no_value(&flag, value)?;
+                    config.whole_word = true;
+                },
+                "line-regexp" => {
+                    no_value(&flag, value)?;
+                    config.whole_line = true;
+                },
+                "normalize" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.normalize = match value.to_lowercase().as_str() {
+                        "nfc" => Some(Form::Nfc),
+                        "nfkc" => Some(Form::Nfkc),
+                        _ => return Err(CliError::InvalidValue(flag, value)),
+                    };
+                },
+                "fold" => {
+                    no_value(&flag, value)?;
+                    config.fold = true;
+                },
+                "fold-ligatures" => {
+                    no_value(&flag, value)?;
+                    config.fold_ligatures = true;
+                },
+                "invert-match" => {
+                    no_value(&flag, value)?;
+                    config.invert = true;
+                },
+                "count" => {
+                    no_value(&flag, value)?;
+                    config.count = true;
+                },
+                "after-context" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.after_context = Some(parse_count(flag, value)?);
+                },
+                "before-context" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.before_context = Some(parse_count(flag, value)?);
+                },
+                "context" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.context = Some(parse_count(flag, value)?);
+                },
+                "max-edits" => {
+                    let value = take_value(&flag, value, &mut args)?;
+                    config.max_edits = Some(parse_count(flag, value)?);
+                },
+                _ => return Err(CliError::UnknownOption(flag)),
+            }
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for (index, c) in arg.char_indices().skip(1) {
+                // the rest of a bundle of short flags is the value of an
+                // option that takes one
+                let rest = &arg[(index + c.len_utf8())..];
+                let value = if rest.is_empty() { None } else { Some(rest.to_string()) };
+                match c {
+                    'h' => return Ok(Command::Help),
+                    'V' => return Ok(Command::Version),
+                    'w' => config.whole_word = true,
+                    'x' => config.whole_line = true,
+                    'v' => config.invert = true,
+                    'c' => config.count = true,
+                    'A' | 'B' | 'C' => {
+                        let flag = format!("-{}", c);
+                        let value = take_value(&flag, value, &mut args)?;
+                        let lines = Some(parse_count(flag, value)?);
+                        match c {
+                            'A' => config.after_context = lines,
+                            'B' => config.before_context = lines,
+                            _ => config.context = lines,
+                        }
+                        break;
+                    },
+                    'r' => {
+                        config.replace = Some(take_value("-r", value, &mut args)?);
+                        break;
+                    },
+                    _ => return Err(CliError::UnknownOption(format!("-{}", c))),
+                }
+            }
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    if config.in_place && config.replace.is_none() {
+        return Err(CliError::Requires("--in-place", "--replace"));
+    }
+    if config.backup_suffix.is_some() && !config.in_place {
+        return Err(CliError::Requires("--backup-suffix", "--in-place"));
+    }
+    if config.fold_ligatures && !config.fold {
+        return Err(CliError::Requires("--fold-ligatures", "--fold"));
+    }
+    if config.wildcard && config.replace.is_some() {
+        return Err(CliError::Conflicts("--wildcard", "--replace"));
+    }
+    if config.max_mismatches.is_some() && config.replace.is_some() {
+        return Err(CliError::Conflicts("--max-mismatches", "--replace"));
+    }
+    if config.max_mismatches.is_some() && config.wildcard {
+        return Err(CliError::Conflicts("--max-mismatches", "--wildcard"));
+    }
+    if config.max_edits.is_some() {
+        if config.replace.is_some() {
+            return Err(CliError::Conflicts("--max-edits", "--replace"));
+        }
+        if config.wildcard {
+            return Err(CliError::Conflicts("--max-edits", "--wildcard"));
+        }
+        if config.max_mismatches.is_some() {
+            return Err(CliError::Conflicts("--max-edits", "--max-mismatches"));
+        }
+    }
+
+    for &(set, option) in [(config.whole_word, "--word-regexp"),
+                           (config.whole_line, "--line-regexp")].iter() {
+        if !set {
+            continue;
+        }
+        if config.wildcard {
+            return Err(CliError::Conflicts(option, "--wildcard"));
+        }
+        if config.max_mismatches.is_some() {
+            return Err(CliError::Conflicts(option, "--max-mismatches"));
+        }
+        if config.max_edits.is_some() {
+            return Err(CliError::Conflicts(option, "--max-edits"));
+        }
+    }
+
+    let line_option = [(config.invert, "--invert-match"),
+                       (config.count, "--count"),
+                       (config.after_context.is_some(), "--after-context"),
+                       (config.before_context.is_some(), "--before-context"),
+                       (config.context.is_some(), "--context")].iter()
+        .find(|&&(set, _)| set)
+        .map(|&(_, option)| option);
+    if let Some(option) = line_option {
+        for &(set, other) in [(config.replace.is_some(), "--replace"),
+                              (config.wildcard, "--wildcard"),
+                              (config.max_mismatches.is_some(), "--max-mismatches"),
+                              (config.max_edits.is_some(), "--max-edits")].iter() {
+            if set {
+                return Err(CliError::Conflicts(option, other));
+            }
+        }
+    }
+
+    if config.fold && config.normalize.is_some() {
+        return Err(CliError::Conflicts("--fold", "--normalize"));
+    }
+    for &(set, option) in [(config.normalize.is_some(), "--normalize"),
+                           (config.fold, "--fold")].iter() {
+        if !set {
+            continue;
+        }
+        for &(set, other) in [(config.replace.is_some(), "--replace"),
+                              (config.wildcard, "--wildcard"),
+                              (config.max_mismatches.is_some(), "--max-mismatches"),
+                              (config.max_edits.is_some(), "--max-edits")].iter() {
+            if set {
+                return Err(CliError::Conflicts(option, other));
+            }
+        }
+        if let Some(other) = line_option {
+            return Err(CliError::Conflicts(option, other));
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    config.pattern = match positional.next() {
+        Some(pattern) => pattern,
+        None => return Err(CliError::MissingArgument("pattern")),
+    };
+    config.files = positional.collect();
+    if config.files.is_empty() {
+        return Err(CliError::MissingArgument("input file"));
+    }
+
+    return Ok(Command::Search(config));
+}
+
+fn take_value<I: Iterator<Item = String>>(flag: &str, value: Option<String>, args: &mut I)
+                                          -> Result<String, CliError> {
+    match value {
+        Some(value) => Ok(value),
+        None => args.next().ok_or(CliError::MissingValue(flag.to_string())),
+    }
+}
+
+fn parse_count(flag: String, value: String) -> Result<usize, CliError> {
+    match value.parse() {
+        Ok(count) => Ok(count),
+        Err(_) => Err(CliError::InvalidValue(flag, value)),
+    }
+}
+
+fn no_value(flag: &str, value: Option<String>) -> Result<(), CliError> {
+    match value {
+        Some(_) => Err(CliError::UnexpectedValue(flag.to_string())),
+        None => Ok(()),
+    }
+}
+
+///
+/// Performs the search described by the configuration.
+/// # Arguments
+/// * `config` - the parsed command line
+///
+pub fn run(config: &Config) -> io::Result<()> {
+    for file_name in config.files.iter() {
+        let file = File::open(file_name).map_err(|e| with_file_name(file_name, e))?;
+        match config.replace {
+            Some(ref replacement) => {
+                let searcher = searcher(config);
+                if config.in_place {
+                    replace_in_place(&searcher, Path::new(file_name), file,
+                                     replacement.as_bytes(), config.backup_suffix.as_ref())
+                        .map_err(|e| with_file_name(file_name, e))?;
+                } else {
+                    let stdout = io::stdout();
+                    searcher.replace_to_writer(file, stdout.lock(), replacement.as_bytes())?;
+                }
+            },
+            None if config.is_line_oriented() => search_lines(config, file)?,
+            None if config.wildcard => search_wildcard(&config.pattern, file)?,
+            None if config.normalize.is_some() => search_normalized(config, file)?,
+            None if config.fold => search_folded(config, file)?,
+            None => if let Some(k) = config.max_mismatches {
+                search_mismatches(&config.pattern, k, file)?;
+            } else if let Some(k) = config.max_edits {
+                search_edits(&config.pattern, k, file)?;
+            } else if config.whole_word || config.whole_line {
+                search_with_conditions(&searcher(config), file)?;
+            } else {
+                search::search_file(&config.pattern, file);
+            },
+        }
+    }
+    return Ok(());
+}
+
+/// The searcher for the pattern with the word and line conditions asked for.
+fn searcher(config: &Config) -> Searcher {
+    return Searcher::new(&config.pattern)
+        .whole_word(config.whole_word)
+        .whole_line(config.whole_line);
+}
+
+///
+/// Prints the offset of every match in the file that meets the searcher's
+/// word and line conditions.
+///
+fn search_with_conditions(searcher: &Searcher, mut file: File) -> io::Result<()> {
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    for offset in searcher.find_iter(&contents) {
+        println!("found match at {:?}", offset);
+    }
+    return Ok(());
+}
+
+///
+/// Prints the lines of the file selected by the configuration with their
+/// context, or only how many there are.
+///
+fn search_lines(config: &Config, mut file: File) -> io::Result<()> {
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    let index = LineIndex::from_bytes(&contents);
+    let selected = grep::selected_lines(&searcher(config), &contents, &index, config.invert);
+    if config.count {
+        println!("{}", selected.len());
+        return Ok(());
+    }
+    let before = config.before_context.or(config.context).unwrap_or(0);
+    let after = config.after_context.or(config.context).unwrap_or(0);
+    let stdout = io::stdout();
+    return grep::write_lines(stdout.lock(), &contents, &index, &selected, before, after);
+}
+
+///
+/// Prints every match in the file after normalizing both it and the pattern,
+/// with the offset and the highlighted text of the original file.
+///
+fn search_normalized(config: &Config, mut file: File) -> io::Result<()> {
+    let form = config.normalize.unwrap_or(Form::Nfc);
+    let searcher = NormalizingSearcher::new(&config.pattern, form)
+        .whole_word(config.whole_word)
+        .whole_line(config.whole_line);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    print_spans(&contents, searcher.find_all(&contents));
+    return Ok(());
+}
+
+///
+/// Prints every match in the file after folding both it and the pattern,
+/// with the offset and the highlighted text of the original file.
+///
+fn search_folded(config: &Config, mut file: File) -> io::Result<()> {
+    let folding = Folding { diacritics: true, width: true, ligatures: config.fold_ligatures };
+    let searcher = FoldingSearcher::new(&config.pattern, folding)
+        .whole_word(config.whole_word)
+        .whole_line(config.whole_line);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    print_spans(&contents, searcher.find_all(&contents));
+    return Ok(());
+}
+
+///
+/// Prints the offset and the highlighted text of each span of the contents.
+/// The line index is only built once there is a span to print.
+///
+fn print_spans(contents: &str, spans: Vec<Range<usize>>) {
+    let mut index = LazyLineIndex::new(contents.as_bytes());
+    for span in spans {
+        println!("found match at {:?}", span.start);
+        search::print_result(contents, span.clone(), index.get().line(span.start));
+    }
+}
+
+///
+/// Prints the offset of every match of a wildcard pattern in the file.
+///
+fn search_wildcard(pattern: &str, mut file: File) -> io::Result<()> {
+    let searcher = WildcardSearcher::new(pattern)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    for offset in searcher.find_iter(&contents) {
+        println!("found match at {:?}", offset);
+    }
+    return Ok(());
+}
+
+///
+/// Prints the offset and mismatch count of every place in the file that
+/// differs from the pattern in at most `max_mismatches` bytes.
+///
+fn search_mismatches(pattern: &str, max_mismatches: usize, mut file: File) -> io::Result<()> {
+    let searcher = MismatchSearcher::new(pattern, max_mismatches);
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    for found in searcher.find_iter(&contents) {
+        println!("found match at {} ({} mismatches)", found.start, found.mismatches);
+    }
+    return Ok(());
+}
+
+///
+/// Prints the span and distance of every place in the file that is within
+/// `max_edits` edits of the pattern. Of the matches ending at consecutive
+/// offsets only the closest is printed, since they are mostly the same
+/// match with a byte more or less.
+///
+fn search_edits(pattern: &str, max_edits: usize, mut file: File) -> io::Result<()> {
+    let searcher = EditSearcher::new(pattern, max_edits);
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    for found in best_edit_matches(searcher.find_iter(&contents)) {
+        let alignment = searcher.align(&contents, found.end);
+        println!("found match at {}..{} ({} edits)", alignment.start, alignment.end,
+                 alignment.distance);
+    }
+    return Ok(());
+}
+
+/// The closest of each run of matches that end at consecutive offsets.
+fn best_edit_matches<I: Iterator<Item = EditMatch>>(matches: I) -> Vec<EditMatch> {
+    let mut best: Vec<EditMatch> = Vec::new();
+    let mut previous_end = None;
+    for found in matches {
+        if previous_end.is_some_and(|end| end + 1 == found.end) {
+            let last = best.len() - 1;
+            if found.distance < best[last].distance {
+                best[last] = found;
+            }
+        } else {
+            best.push(found);
+        }
+        previous_end = Some(found.end);
+    }
+    return best;
+}
+
+fn with_file_name(file_name: &str, e: io::Error) -> io::Error {
+    return io::Error::new(e.kind(), format!("{}: {}", file_name, e));
+}
+
+///
+/// Rewrites the file with every match replaced. The new contents are written
+/// to a temporary file next to the original which is then renamed over it, so
+/// the file is never left half written.
+/// # Arguments
+/// * `searcher` - the pattern to replace
+/// * `path` - the file being rewritten
+/// * `file` - the open original file
+/// * `replacement` - what every match is replaced with
+/// * `backup_suffix` - if given, the original is first copied to `path` + suffix
+///
+fn replace_in_place(searcher: &Searcher, path: &Path, file: File, replacement: &[u8],
+                    backup_suffix: Option<&String>) -> io::Result<()> {
+    let temp_path = sibling_path(path, ".", &format!(".tmp{}", process::id()));
+    let result = File::create(&temp_path).and_then(|temp| {
+        temp.set_permissions(file.metadata()?.permissions())?;
+        searcher.replace_to_writer(io::BufReader::new(file), io::BufWriter::new(&temp),
+                                   replacement)?;
+        return temp.sync_all();
+    });
+
+    let result = result.and_then(|_| {
+        if let Some(suffix) = backup_suffix {
+            fs::copy(path, sibling_path(path, "", suffix))?;
+        }
+        return fs::rename(&temp_path, path);
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    return result;
+}
+
+/// Path in the same directory as `path` with its file name wrapped in `prefix` and `suffix`.
+fn sibling_path(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    return path.with_file_name(format!("{}{}{}", prefix, name, suffix));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn args(input: &[&str]) -> Vec<String> {
+        return input.iter().map(|s| s.to_string()).collect();
+    }
+
+    fn config(input: &[&str]) -> Config {
+        match parse(args(input)) {
+            Ok(Command::Search(config)) => config,
+            other => panic!("expected a search, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pattern_and_file() {
+        let config = config(&["pattern", "input.txt"]);
+        assert_eq!("pattern", config.pattern);
+        assert_eq!(vec!["input.txt".to_string()], config.files);
+    }
+
+    #[test]
+    fn multiple_files() {
+        let config = config(&["pattern", "a.txt", "b.txt"]);
+        assert_eq!(2, config.files.len(), "both files kept");
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(Ok(Command::Help), parse(args(&["--help"])));
+        assert_eq!(Ok(Command::Help), parse(args(&["pattern", "-h"])));
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(Ok(Command::Version), parse(args(&["--version"])));
+        assert_eq!(Ok(Command::Version), parse(args(&["-V"])));
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let config = config(&["--", "-h", "--version"]);
+        assert_eq!("-h", config.pattern, "dash pattern kept");
+        assert_eq!(vec!["--version".to_string()], config.files);
+    }
+
+    #[test]
+    fn single_dash_is_positional() {
+        let config = config(&["-", "input.txt"]);
+        assert_eq!("-", config.pattern);
+    }
+
+    #[test]
+    fn unknown_long_option() {
+        let result = parse(args(&["--bogus", "pattern", "input.txt"]));
+        assert_eq!(Err(CliError::UnknownOption("--bogus".to_string())), result);
+    }
+
+    #[test]
+    fn unknown_short_option() {
+        let result = parse(args(&["-q", "pattern", "input.txt"]));
+        assert_eq!(Err(CliError::UnknownOption("-q".to_string())), result);
+    }
+
+    #[test]
+    fn flag_with_value() {
+        let result = parse(args(&["--help=yes"]));
+        assert_eq!(Err(CliError::UnexpectedValue("--help".to_string())), result);
+    }
+
+    #[test]
+    fn replace_options() {
+        let config = config(&["--replace", "new", "--in-place", "--backup-suffix=.bak",
+                              "old", "input.txt"]);
+        assert_eq!(Some("new".to_string()), config.replace);
+        assert!(config.in_place, "in place set");
+        assert_eq!(Some(".bak".to_string()), config.backup_suffix);
+    }
+
+    #[test]
+    fn short_option_values() {
+        assert_eq!(Some("new".to_string()), config(&["-r", "new", "old", "a.txt"]).replace);
+        assert_eq!(Some("new".to_string()), config(&["-rnew", "old", "a.txt"]).replace);
+    }
+
+    #[test]
+    fn missing_value() {
+        let result = parse(args(&["old", "input.txt", "--replace"]));
+        assert_eq!(Err(CliError::MissingValue("--replace".to_string())), result);
+    }
+
+    #[test]
+    fn in_place_requires_replace() {
+        let result = parse(args(&["--in-place", "old", "input.txt"]));
+        assert_eq!(Err(CliError::Requires("--in-place", "--replace")), result);
+    }
+
+    #[test]
+    fn replace_file_in_place() {
+        let dir = env::temp_dir().join(format!("boyer_moore_cli_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input.txt");
+        fs::write(&path, "old text, old ways").unwrap();
+
+        let config = config(&["-r", "new", "--in-place", "--backup-suffix", ".orig",
+                              "old", path.to_str().unwrap()]);
+        run(&config).unwrap();
+
+        assert_eq!("new text, new ways", fs::read_to_string(&path).unwrap());
+        assert_eq!("old text, old ways",
+                   fs::read_to_string(dir.join("input.txt.orig")).unwrap(), "backup kept");
+        assert_eq!(2, fs::read_dir(&dir).unwrap().count(), "temporary file removed");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wildcard_option() {
+        assert!(config(&["--wildcard", "ID-??", "a.txt"]).wildcard, "wildcard set");
+        assert!(!config(&["ID-??", "a.txt"]).wildcard, "literal by default");
+        let result = parse(args(&["--wildcard", "-r", "x", "ID-??", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--wildcard", "--replace")), result);
+    }
+
+    #[test]
+    fn max_mismatches_option() {
+        assert_eq!(Some(2), config(&["--max-mismatches", "2", "invoice", "a.txt"]).max_mismatches);
+        assert_eq!(Some(0), config(&["--max-mismatches=0", "invoice", "a.txt"]).max_mismatches);
+        assert_eq!(None, config(&["invoice", "a.txt"]).max_mismatches);
+    }
+
+    #[test]
+    fn invalid_max_mismatches() {
+        let result = parse(args(&["--max-mismatches", "-1", "invoice", "a.txt"]));
+        assert_eq!(Err(CliError::InvalidValue("--max-mismatches".to_string(), "-1".to_string())),
+                   result);
+        let result = parse(args(&["--max-mismatches", "1", "--wildcard", "ID-??", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--max-mismatches", "--wildcard")), result);
+        let result = parse(args(&["--max-mismatches", "1", "-r", "x", "invoice", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--max-mismatches", "--replace")), result);
+    }
+
+    #[test]
+    fn max_edits_option() {
+        assert_eq!(Some(1), config(&["--max-edits", "1", "color", "a.txt"]).max_edits);
+        let result = parse(args(&["--max-edits=x", "color", "a.txt"]));
+        assert_eq!(Err(CliError::InvalidValue("--max-edits".to_string(), "x".to_string())), result);
+        let result = parse(args(&["--max-edits", "1", "--max-mismatches", "1", "color", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--max-edits", "--max-mismatches")), result);
+    }
+
+    #[test]
+    fn closest_of_consecutive_edit_matches() {
+        let matches = vec![EditMatch { end: 5, distance: 1 }, EditMatch { end: 6, distance: 0 },
+                           EditMatch { end: 7, distance: 1 }, EditMatch { end: 12, distance: 1 }];
+        assert_eq!(vec![EditMatch { end: 6, distance: 0 }, EditMatch { end: 12, distance: 1 }],
+                   best_edit_matches(matches.into_iter()));
+    }
+
+    #[test]
+    fn word_and_line_options() {
+        let bundled = config(&["-wx", "test", "a.txt"]);
+        assert!(bundled.whole_word && bundled.whole_line, "both set from a bundle");
+        let long = config(&["--word-regexp", "--line-regexp", "test", "a.txt"]);
+        assert!(long.whole_word && long.whole_line, "both set from long options");
+        let result = parse(args(&["-w", "--wildcard", "te?t", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--word-regexp", "--wildcard")), result);
+        let result = parse(args(&["-x", "--max-edits", "1", "test", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--line-regexp", "--max-edits")), result);
+    }
+
+    #[test]
+    fn line_options() {
+        let lines = config(&["-vc", "-A", "1", "-B2", "--context=3", "ERROR", "a.log"]);
+        assert!(lines.invert && lines.count, "flags set from a bundle");
+        assert_eq!((Some(1), Some(2), Some(3)),
+                   (lines.after_context, lines.before_context, lines.context));
+        assert!(lines.is_line_oriented(), "line oriented");
+        assert!(!config(&["-w", "ERROR", "a.log"]).is_line_oriented(), "match oriented");
+    }
+
+    #[test]
+    fn line_option_conflicts() {
+        let result = parse(args(&["-v", "-r", "x", "ERROR", "a.log"]));
+        assert_eq!(Err(CliError::Conflicts("--invert-match", "--replace")), result);
+        let result = parse(args(&["-C", "2", "--max-edits", "1", "ERROR", "a.log"]));
+        assert_eq!(Err(CliError::Conflicts("--context", "--max-edits")), result);
+        let result = parse(args(&["-A", "x", "ERROR", "a.log"]));
+        assert_eq!(Err(CliError::InvalidValue("-A".to_string(), "x".to_string())), result);
+    }
+
+    #[test]
+    fn normalize_option() {
+        assert_eq!(Some(Form::Nfc), config(&["--normalize", "nfc", "café", "a.txt"]).normalize);
+        assert_eq!(Some(Form::Nfkc), config(&["--normalize=NFKC", "café", "a.txt"]).normalize);
+        let result = parse(args(&["--normalize", "nfd", "café", "a.txt"]));
+        assert_eq!(Err(CliError::InvalidValue("--normalize".to_string(), "nfd".to_string())),
+                   result);
+        let result = parse(args(&["--normalize", "nfc", "-c", "café", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--normalize", "--count")), result);
+    }
+
+    #[test]
+    fn fold_options() {
+        let folded = config(&["--fold", "--fold-ligatures", "-w", "resume", "a.txt"]);
+        assert!(folded.fold && folded.fold_ligatures && folded.whole_word, "flags set");
+        let result = parse(args(&["--fold-ligatures", "file", "a.txt"]));
+        assert_eq!(Err(CliError::Requires("--fold-ligatures", "--fold")), result);
+        let result = parse(args(&["--fold", "--normalize", "nfc", "resume", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--fold", "--normalize")), result);
+        let result = parse(args(&["--fold", "-v", "resume", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--fold", "--invert-match")), result);
+        let result = parse(args(&["--fold", "--max-edits", "1", "resume", "a.txt"]));
+        assert_eq!(Err(CliError::Conflicts("--fold", "--max-edits")), result);
+    }
+
+    #[test]
+    fn missing_pattern() {
+        assert_eq!(Err(CliError::MissingArgument("pattern")), parse(args(&[])));
+    }
+
+    #[test]
+    fn missing_file() {
+        assert_eq!(Err(CliError::MissingArgument("input file")), parse(args(&["pattern"])));
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!("unrecognized option '--bogus'",
+                   CliError::UnknownOption("--bogus".to_string()).to_string());
+        assert_eq!("missing required argument [pattern]",
+                   CliError::MissingArgument("pattern").to_string());
+    }
+}
@@ -1,2 +1,3 @@
 #![feature(io)]
+pub mod cli;
 pub mod search;
@@ -3,21 +3,27 @@ extern crate boyer_moore;
 
 use std::env;
 use std::process;
-use std::fs::File;
 
-use boyer_moore::search::search;
+use boyer_moore::cli;
+use boyer_moore::cli::Command;
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let mut args = env::args();
+    let program = args.next().unwrap_or("search".to_string());
 
-    if args.len() < 3 {
-        println!("not enough arguments, run {} [pattern] [input file]", args[0]);
-        process::exit(1);
+    match cli::parse(args) {
+        Ok(Command::Help) => print!("{}", cli::usage(&program)),
+        Ok(Command::Version) => println!("search {}", cli::VERSION),
+        Ok(Command::Search(config)) => {
+            if let Err(e) = cli::run(&config) {
+                eprintln!("{}: {}", program, e);
+                process::exit(2);
+            }
+        },
+        Err(e) => {
+            eprintln!("{}: {}", program, e);
+            eprintln!("Try '{} --help' for more information.", program);
+            process::exit(1);
+        },
     }
-
-    let ref pattern = args[1];
-    let ref file_name =  args[2];
-    let file: File = File::open(file_name).unwrap();
-
-    search::search_file(pattern, file);
 }
@@ -0,0 +1,143 @@
+//!
+//! Tests for where a match sits in the text: against a word or on its own,
+//! at the start or end of a line.
+//!
+//! A word character is a Unicode letter or digit, or `_`. Bytes that are
+//! not valid UTF-8 are not word characters. Lines end at `\n`, `\r\n` or a
+//! lone `\r`, like in `LineIndex`.
+//!
+//! ```
+//! use boyer_moore::search::boundary;
+//!
+//! let text = "latest test".as_bytes();
+//! assert!(!boundary::is_word_boundary(text, 2));
+//! assert!(boundary::is_word_boundary(text, 7));
+//! assert!(boundary::is_line_end(text, text.len()));
+//! ```
+
+use std::str;
+
+/// The longest UTF-8 encoding of a character, in bytes.
+pub(crate) const MAX_CHAR_LEN: usize = 4;
+
+/// Whether the character is part of a word.
+pub fn is_word_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_';
+}
+
+///
+/// The character that starts at the offset, if the bytes there are valid
+/// UTF-8.
+///
+fn char_after(haystack: &[u8], offset: usize) -> Option<char> {
+    let end = haystack.len().min(offset + MAX_CHAR_LEN);
+    let bytes = &haystack[offset..end];
+    let valid = match str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),
+    };
+    return valid.chars().next();
+}
+
+///
+/// The character that ends at the offset, if the bytes there are valid
+/// UTF-8.
+///
+fn char_before(haystack: &[u8], offset: usize) -> Option<char> {
+    let start = offset.saturating_sub(MAX_CHAR_LEN);
+    // the character starts at the last byte that is not a continuation byte
+    let lead = (start..offset).rev().find(|&i| haystack[i] & 0xC0 != 0x80)?;
+    return str::from_utf8(&haystack[lead..offset]).ok()?.chars().next();
+}
+
+/// Whether a word character ends at the offset.
+pub fn word_before(haystack: &[u8], offset: usize) -> bool {
+    return char_before(haystack, offset).is_some_and(is_word_char);
+}
+
+/// Whether a word character starts at the offset.
+pub fn word_after(haystack: &[u8], offset: usize) -> bool {
+    return char_after(haystack, offset).is_some_and(is_word_char);
+}
+
+///
+/// Whether the offset is the start or end of a word, that is, a word
+/// character is on exactly one side of it.
+///
+pub fn is_word_boundary(haystack: &[u8], offset: usize) -> bool {
+    return word_before(haystack, offset) != word_after(haystack, offset);
+}
+
+///
+/// Whether the offset is the start of a line: the start of the text or just
+/// after a line break.
+///
+pub fn is_line_start(haystack: &[u8], offset: usize) -> bool {
+    if offset == 0 {
+        return true;
+    }
+    return match haystack[offset - 1] {
+        b'\n' => true,
+        // the middle of a `\r\n` is inside the line break
+        b'\r' => haystack.get(offset) != Some(&b'\n'),
+        _ => false,
+    };
+}
+
+///
+/// Whether the offset is the end of a line: the end of the text or just
+/// before a line break.
+///
+pub fn is_line_end(haystack: &[u8], offset: usize) -> bool {
+    return match haystack.get(offset) {
+        None | Some(&b'\r') => true,
+        Some(&b'\n') => offset == 0 || haystack[offset - 1] != b'\r',
+        Some(_) => false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_boundaries() {
+        let text = b"a test_1, ok";
+        let boundaries: Vec<usize> = (0..text.len() + 1)
+            .filter(|&i| is_word_boundary(text, i)).collect();
+        assert_eq!(vec![0, 1, 2, 8, 10, 12], boundaries);
+    }
+
+    #[test]
+    fn unicode_words() {
+        let text = "naïve café".as_bytes();
+        assert!(!is_word_boundary(text, 3), "before the diaeresis");
+        assert!(!is_word_boundary(text, 5), "after the diaeresis");
+        assert!(is_word_boundary(text, text.len()));
+        assert!(word_before(text, text.len()));
+        assert!(!word_after("…x".as_bytes(), 0));
+    }
+
+    #[test]
+    fn invalid_utf8_is_not_a_word() {
+        let text = b"a\xffb";
+        assert!(is_word_boundary(text, 1));
+        assert!(is_word_boundary(text, 2));
+        assert!(!word_before(&text[..1], 0));
+    }
+
+    #[test]
+    fn line_starts() {
+        let text = b"a\nb\r\nc\rd";
+        let starts: Vec<usize> = (0..text.len() + 1).filter(|&i| is_line_start(text, i)).collect();
+        assert_eq!(vec![0, 2, 5, 7], starts);
+    }
+
+    #[test]
+    fn line_ends() {
+        let text = b"a\nb\r\nc\rd";
+        let ends: Vec<usize> = (0..text.len() + 1).filter(|&i| is_line_end(text, i)).collect();
+        assert_eq!(vec![1, 3, 6, 8], ends);
+        assert!(is_line_end(b"\n", 0));
+    }
+}
@@ -0,0 +1,403 @@
+//!
+//! Approximate search that allows insertions and deletions as well as
+//! substitutions, that is, an edit (Levenshtein) distance, between the
+//! pattern and the text it matches.
+//!
+//! The search runs the dynamic programming table of the distances between
+//! the pattern and every substring of the text one column per byte of text,
+//! with Ukkonen's cut-off: rows below the last one that is still within `k`
+//! are left alone, so the search takes about `O(kn)` rather than `O(mn)`.
+//! Only the end of each match and its distance are found this way; `align`
+//! recovers where it starts and which edits it takes.
+//!
+//! ```
+//! use boyer_moore::search::edit::{EditOp, EditSearcher};
+//!
+//! let searcher = EditSearcher::new("receive", 1);
+//! let found = searcher.find_iter("we recieve, we recive").last().unwrap();
+//! assert_eq!((21, 1), (found.end, found.distance));
+//!
+//! let alignment = searcher.align("we recieve, we recive", found.end);
+//! assert_eq!(15, alignment.start);
+//! assert_eq!(EditOp::Delete, alignment.operations[3]);
+//! ```
+
+use std::cmp::min;
+
+use search::searcher::Searcher;
+
+///
+/// Where an approximate match ends, exclusive, and the fewest edits that
+/// turn some substring ending there into the pattern.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EditMatch {
+    pub end: usize,
+    pub distance: usize,
+}
+
+///
+/// One step of an alignment between the pattern and the text.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EditOp {
+    /// the pattern byte and the text byte are the same
+    Match,
+    /// the pattern byte is replaced by a different text byte
+    Substitute,
+    /// the text has a byte that is not in the pattern
+    Insert,
+    /// the text is missing a byte of the pattern
+    Delete,
+}
+
+///
+/// The span of text an approximate match covers and how it lines up with
+/// the pattern.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alignment {
+    pub start: usize,
+    pub end: usize,
+    pub distance: usize,
+    /// the edits from the start of the pattern to its end, one per pattern
+    /// byte or inserted text byte
+    pub operations: Vec<EditOp>,
+}
+
+///
+/// A searcher for the substrings of the text within edit distance `k` of
+/// the pattern.
+///
+#[derive(Clone)]
+pub struct EditSearcher {
+    pattern: Vec<u8>,
+    max_distance: usize,
+}
+
+impl EditSearcher {
+
+    ///
+    /// Builds the searcher for the pattern, allowing up to `max_distance`
+    /// inserted, deleted or substituted bytes.
+    ///
+    pub fn new(pattern: &str, max_distance: usize) -> EditSearcher {
+        return EditSearcher::from_bytes(pattern.as_bytes(), max_distance);
+    }
+
+    ///
+    /// Builds the searcher for a pattern that is not necessarily UTF-8.
+    ///
+    pub fn from_bytes(pattern: &[u8], max_distance: usize) -> EditSearcher {
+        return EditSearcher { pattern: pattern.to_vec(), max_distance: max_distance };
+    }
+
+    /// The number of edits a match may have.
+    pub fn max_distance(&self) -> usize {
+        return self.max_distance;
+    }
+
+    ///
+    /// Returns every offset at which a substring within the distance of the
+    /// pattern ends, in increasing order, with the smallest distance of such
+    /// a substring. When the pattern is no longer than `k` bytes the empty
+    /// substrings match too, so every offset is reported.
+    ///
+    pub fn find_iter<'s, 'h, H: AsRef<[u8]> + ?Sized>(&'s self, haystack: &'h H)
+                                                     -> EditIter<'s, 'h> {
+        let m = self.pattern.len();
+        return EditIter {
+            searcher: self,
+            haystack: haystack.as_ref(),
+            column: (0..m + 1).collect(),
+            last: min(self.max_distance, m),
+            position: 0,
+            started: false,
+        };
+    }
+
+    ///
+    /// Recovers the span and the edits of the match that ends at `end`.
+    /// # Arguments
+    /// * `haystack` - the body that was searched
+    /// * `end` - the end of a match, as reported by `find_iter`
+    ///
+    /// # Result
+    /// The alignment of the pattern against the closest substring ending at
+    /// `end`; for an offset that is not the end of a match its distance is
+    /// larger than the limit
+    ///
+    /// # Panics
+    /// If `end` is past the end of the haystack
+    ///
+    pub fn align<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H, end: usize) -> Alignment {
+        let haystack = haystack.as_ref();
+        assert!(end <= haystack.len(), "end {} is past the haystack", end);
+        let m = self.pattern.len();
+        // a match is no longer than the pattern plus one insertion per edit
+        let first = end.saturating_sub(m + self.max_distance);
+        let text = &haystack[first..end];
+        let n = text.len();
+
+        // the distances between each prefix of the pattern and each
+        // substring of the window that ends at the column
+        let mut table = vec![vec![0; n + 1]; m + 1];
+        for i in 1..m + 1 {
+            table[i][0] = i;
+            for j in 1..n + 1 {
+                let cost = if self.pattern[i - 1] == text[j - 1] { 0 } else { 1 };
+                table[i][j] = min(table[i - 1][j - 1] + cost,
+                                  min(table[i - 1][j], table[i][j - 1]) + 1);
+            }
+        }
+
+        let mut operations = Vec::new();
+        let (mut i, mut j) = (m, n);
+        while i > 0 {
+            let here = table[i][j];
+            if j > 0 && self.pattern[i - 1] == text[j - 1] && here == table[i - 1][j - 1] {
+                operations.push(EditOp::Match);
+                i -= 1;
+                j -= 1;
+            } else if j > 0 && here == table[i - 1][j - 1] + 1 {
+                operations.push(EditOp::Substitute);
+                i -= 1;
+                j -= 1;
+            } else if here == table[i - 1][j] + 1 {
+                operations.push(EditOp::Delete);
+                i -= 1;
+            } else {
+                operations.push(EditOp::Insert);
+                j -= 1;
+            }
+        }
+        operations.reverse();
+        return Alignment { start: first + j, end: end, distance: table[m][n], operations: operations };
+    }
+}
+
+impl Searcher {
+
+    ///
+    /// A searcher for the same pattern that also accepts occurrences with up
+    /// to `max_distance` inserted, deleted or substituted bytes.
+    ///
+    pub fn with_max_distance(&self, max_distance: usize) -> EditSearcher {
+        return EditSearcher::from_bytes(self.pattern(), max_distance);
+    }
+}
+
+///
+/// Iterator over the ends of the approximate matches of a pattern, in
+/// increasing order.
+///
+pub struct EditIter<'s, 'h> {
+    searcher: &'s EditSearcher,
+    haystack: &'h [u8],
+    /// the distances between each prefix of the pattern and the closest
+    /// substring ending at `position`; only exact up to row `last`, below
+    /// which they are known to be more than `k`
+    column: Vec<usize>,
+    /// the last row of the column that is within `k`
+    last: usize,
+    position: usize,
+    /// whether the column before the first byte has been looked at
+    started: bool,
+}
+
+impl<'s, 'h> EditIter<'s, 'h> {
+
+    /// Moves the column on by one byte of the text.
+    fn advance(&mut self) {
+        let m = self.searcher.pattern.len();
+        let k = self.searcher.max_distance;
+        let byte = self.haystack[self.position];
+        self.position += 1;
+
+        // the first row stays zero, since a match may start anywhere
+        let mut diagonal = 0;
+        let rows = min(self.last + 1, m);
+        for i in 1..rows + 1 {
+            let cost = if self.searcher.pattern[i - 1] == byte { 0 } else { 1 };
+            let above = self.column[i];
+            self.column[i] = min(diagonal + cost, min(above, self.column[i - 1]) + 1);
+            diagonal = above;
+        }
+
+        if rows > self.last && self.column[rows] <= k {
+            self.last = rows;
+        }
+        while self.last > 0 && self.column[self.last] > k {
+            self.last -= 1;
+        }
+    }
+}
+
+impl<'s, 'h> Iterator for EditIter<'s, 'h> {
+    type Item = EditMatch;
+
+    fn next(&mut self) -> Option<EditMatch> {
+        let m = self.searcher.pattern.len();
+        if m == 0 {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            if self.last == m {
+                return Some(EditMatch { end: 0, distance: self.column[m] });
+            }
+        }
+        while self.position < self.haystack.len() {
+            self.advance();
+            if self.last == m {
+                return Some(EditMatch { end: self.position, distance: self.column[m] });
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    /// The edit distance between two strings, by the full table.
+    fn distance(a: &[u8], b: &[u8]) -> usize {
+        let mut row: Vec<usize> = (0..b.len() + 1).collect();
+        for i in 1..a.len() + 1 {
+            let mut diagonal = row[0];
+            row[0] = i;
+            for j in 1..b.len() + 1 {
+                let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                let above = row[j];
+                row[j] = min(diagonal + cost, min(above, row[j - 1]) + 1);
+                diagonal = above;
+            }
+        }
+        return row[b.len()];
+    }
+
+    /// Every end with the closest substring ending there, checked one by one.
+    fn naive(pattern: &[u8], haystack: &[u8], k: usize) -> Vec<EditMatch> {
+        let mut result = Vec::new();
+        if pattern.is_empty() {
+            return result;
+        }
+        for end in 0..haystack.len() + 1 {
+            let best = (0..end + 1).map(|start| distance(pattern, &haystack[start..end])).min().unwrap();
+            if best <= k {
+                result.push(EditMatch { end: end, distance: best });
+            }
+        }
+        return result;
+    }
+
+    /// Applies the operations to the pattern, giving the text they cover.
+    fn apply(pattern: &[u8], text: &[u8], operations: &[EditOp]) -> (usize, usize) {
+        let (mut i, mut j, mut edits) = (0, 0, 0);
+        for operation in operations {
+            match *operation {
+                EditOp::Match => {
+                    assert_eq!(pattern[i], text[j]);
+                    i += 1;
+                    j += 1;
+                },
+                EditOp::Substitute => {
+                    assert!(pattern[i] != text[j]);
+                    i += 1;
+                    j += 1;
+                    edits += 1;
+                },
+                EditOp::Insert => {
+                    j += 1;
+                    edits += 1;
+                },
+                EditOp::Delete => {
+                    i += 1;
+                    edits += 1;
+                },
+            }
+        }
+        assert_eq!(pattern.len(), i);
+        return (j, edits);
+    }
+
+    #[test]
+    fn exact_when_no_edits() {
+        let searcher = EditSearcher::new("abc", 0);
+        let ends: Vec<usize> = searcher.find_iter("abcabd abc").map(|m| m.end).collect();
+        assert_eq!(vec![3, 10], ends);
+    }
+
+    #[test]
+    fn insertion_and_deletion() {
+        let searcher = EditSearcher::new("color", 1);
+        assert_eq!(Some(6), searcher.find_iter("colour").map(|m| m.end).last());
+        assert_eq!(Some(EditMatch { end: 4, distance: 1 }), searcher.find_iter("colr").last());
+    }
+
+    #[test]
+    fn align_insertion() {
+        let searcher = EditSearcher::new("color", 1);
+        let alignment = searcher.align("the colour", 10);
+        assert_eq!(4, alignment.start);
+        assert_eq!(1, alignment.distance);
+        assert_eq!(vec![EditOp::Match, EditOp::Match, EditOp::Match, EditOp::Match,
+                        EditOp::Insert, EditOp::Match], alignment.operations);
+    }
+
+    #[test]
+    fn short_pattern_matches_everywhere() {
+        let searcher = EditSearcher::new("ab", 2);
+        let matches: Vec<EditMatch> = searcher.find_iter("xab").collect();
+        assert_eq!(vec![EditMatch { end: 0, distance: 2 }, EditMatch { end: 1, distance: 2 },
+                        EditMatch { end: 2, distance: 1 }, EditMatch { end: 3, distance: 0 }],
+                   matches);
+        assert_eq!(0, searcher.align("xab", 0).start);
+    }
+
+    #[test]
+    fn empty_pattern() {
+        assert_eq!(0, EditSearcher::new("", 0).find_iter("abc").count());
+        assert_eq!(0, EditSearcher::new("", 2).find_iter("abc").count());
+    }
+
+    #[test]
+    fn from_searcher() {
+        let searcher = Searcher::new("needle").with_max_distance(2);
+        assert_eq!(2, searcher.max_distance());
+        assert_eq!(Some(EditMatch { end: 9, distance: 1 }), searcher.find_iter("hay nedle").last());
+    }
+
+    #[test]
+    fn matches_naive_search() {
+        let mut random = Random::new(46);
+        for _ in 0..1500 {
+            let haystack = random.bytes(b"abc", 20);
+            let pattern = random.bytes(b"abc", 6);
+            let k = random.next(4);
+            let searcher = EditSearcher::from_bytes(&pattern, k);
+            assert_eq!(naive(&pattern, &haystack, k), searcher.find_iter(&haystack).collect::<Vec<_>>(),
+                       "{:?} in {:?} with {}", pattern, haystack, k);
+        }
+    }
+
+    #[test]
+    fn alignments_cover_their_matches() {
+        let mut random = Random::new(460);
+        for _ in 0..1500 {
+            let haystack = random.bytes(b"abc", 20);
+            let pattern = random.bytes(b"abc", 6);
+            let k = random.next(4);
+            let searcher = EditSearcher::from_bytes(&pattern, k);
+            for found in searcher.find_iter(&haystack) {
+                let alignment = searcher.align(&haystack, found.end);
+                assert_eq!(found.distance, alignment.distance);
+                let span = &haystack[alignment.start..alignment.end];
+                assert_eq!((span.len(), found.distance), apply(&pattern, span, &alignment.operations),
+                           "{:?} in {:?} ending at {}", pattern, haystack, found.end);
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//!
+//! Matching that overlooks differences users do not think of as different:
+//! accents and other nonspacing marks, full-width and half-width forms of
+//! the same character and, when asked for, typographic ligatures.
+//!
+//! The pattern and the text are both folded before searching, and the text
+//! keeps the way back to its original offsets like a `Normalized` text does,
+//! so matches still cover the characters as they were written.
+//!
+//! ```
+//! use boyer_moore::search::fold::{Folding, FoldingSearcher};
+//!
+//! let searcher = FoldingSearcher::new("resume", Folding::default());
+//! assert_eq!(vec![4..12], searcher.find_all("see résumé"));
+//! ```
+
+extern crate unicode_general_category;
+extern crate unicode_normalization;
+
+use std::ops::Range;
+
+use self::unicode_general_category::get_general_category;
+use self::unicode_general_category::GeneralCategory;
+use self::unicode_normalization::char::decompose_canonical;
+use self::unicode_normalization::char::decompose_compatible;
+use self::unicode_normalization::UnicodeNormalization;
+
+use search::normalize::Normalized;
+use search::searcher::Searcher;
+
+///
+/// Which differences between characters a search overlooks.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Folding {
+    /// strip accents and other nonspacing marks, so "é" is "e"
+    pub diacritics: bool,
+    /// fold full-width and half-width forms, so "Ａ" is "A" and "ｶ" is "カ"
+    pub width: bool,
+    /// split typographic ligatures, so "ﬁ" is "fi"
+    pub ligatures: bool,
+}
+
+impl Default for Folding {
+
+    /// Folds diacritics and widths, but not ligatures.
+    fn default() -> Folding {
+        return Folding { diacritics: true, width: true, ligatures: false };
+    }
+}
+
+impl Folding {
+
+    ///
+    /// Returns the folded text. Whatever is folded, the result is in NFC,
+    /// so precomposed and decomposed characters are the same.
+    ///
+    /// ```
+    /// use boyer_moore::search::fold::Folding;
+    ///
+    /// let all = Folding { diacritics: true, width: true, ligatures: true };
+    /// assert_eq!("naive file ABC ガ", all.fold("naïve ﬁle ＡＢＣ ｶﾞ"));
+    /// ```
+    ///
+    pub fn fold(&self, text: &str) -> String {
+        let mut decomposed = String::with_capacity(text.len());
+        {
+            let mut push = |c: char| {
+                if !(self.diacritics && is_diacritic(c)) {
+                    decomposed.push(c);
+                }
+            };
+            for c in text.chars() {
+                if (self.width && is_width_variant(c)) || (self.ligatures && is_ligature(c)) {
+                    decompose_compatible(c, &mut push);
+                } else {
+                    decompose_canonical(c, &mut push);
+                }
+            }
+        }
+        return decomposed.nfc().collect();
+    }
+}
+
+///
+/// Whether the character is a full-width or half-width form of another, or
+/// the ideographic space.
+///
+fn is_width_variant(c: char) -> bool {
+    return c == '\u{3000}' || ('\u{FF00}'..='\u{FFEF}').contains(&c);
+}
+
+/// Whether the character is one of the Latin ligatures such as "ﬁ".
+fn is_ligature(c: char) -> bool {
+    return ('\u{FB00}'..='\u{FB06}').contains(&c);
+}
+
+///
+/// Whether the character is a diacritic that folding strips: a nonspacing
+/// mark other than a kana voicing mark. Spacing marks such as the Devanagari
+/// vowel signs in "किताब" are letters of the word and are kept, and so are
+/// the voicing marks, which make a different syllable, "ガ" rather than "カ".
+///
+fn is_diacritic(c: char) -> bool {
+    if c == '\u{3099}' || c == '\u{309A}' {
+        return false;
+    }
+    return get_general_category(c) == GeneralCategory::NonspacingMark;
+}
+
+///
+/// A searcher that finds the pattern whatever the differences the folding
+/// overlooks.
+///
+#[derive(Clone)]
+pub struct FoldingSearcher {
+    searcher: Searcher,
+    folding: Folding,
+}
+
+impl FoldingSearcher {
+
+    ///
+    /// Builds the searcher for the pattern, comparing folded characters.
+    ///
+    pub fn new(pattern: &str, folding: Folding) -> FoldingSearcher {
+        return FoldingSearcher { searcher: Searcher::new(&folding.fold(pattern)), folding: folding };
+    }
+
+    /// Sets whether a match must be a whole word, in the folded text.
+    pub fn whole_word(mut self, whole_word: bool) -> FoldingSearcher {
+        self.searcher = self.searcher.whole_word(whole_word);
+        return self;
+    }
+
+    /// Sets whether a match must be a whole line.
+    pub fn whole_line(mut self, whole_line: bool) -> FoldingSearcher {
+        self.searcher = self.searcher.whole_line(whole_line);
+        return self;
+    }
+
+    ///
+    /// Returns the bytes of the original text covered by every
+    /// non-overlapping match.
+    ///
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        let folding = self.folding;
+        let folded = Normalized::with_transform(text, |piece| folding.fold(piece));
+        return folded.find_all(&self.searcher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::normalize::naive_count;
+    use search::test_util::Random;
+
+    const NOTHING: Folding = Folding { diacritics: false, width: false, ligatures: false };
+    const EVERYTHING: Folding = Folding { diacritics: true, width: true, ligatures: true };
+
+    fn find(pattern: &str, text: &str, folding: Folding) -> Vec<Range<usize>> {
+        return FoldingSearcher::new(pattern, folding).find_all(text);
+    }
+
+    #[test]
+    fn diacritics() {
+        let folding = Folding::default();
+        assert_eq!(vec![0..8, 9..19], find("resume", "résumé re\u{301}sume\u{301}", folding));
+        assert_eq!(vec![0..6], find("résumé", "resume", folding));
+        assert_eq!(Vec::<Range<usize>>::new(), find("resume", "résumé", NOTHING));
+    }
+
+    #[test]
+    fn full_width() {
+        let folding = Folding::default();
+        assert_eq!(vec![3..12], find("ABC", "abcＡＢＣ", folding));
+        assert_eq!(vec![0..1], find("Ａ", "A", folding));
+    }
+
+    #[test]
+    fn half_width_katakana() {
+        let folding = Folding::default();
+        // the half-width voiced sound mark is a character of its own
+        assert_eq!(vec![0..15], find("ガイド", "ｶﾞｲﾄﾞ", folding));
+        assert_eq!(vec![0..9], find("ｶﾞｲﾄﾞ", "ガイド", folding));
+    }
+
+    #[test]
+    fn voicing_is_not_a_diacritic() {
+        let folding = Folding::default();
+        assert_eq!(Vec::<Range<usize>>::new(), find("カ", "ガ", folding));
+        assert_eq!(Vec::<Range<usize>>::new(), find("カ", "ｶﾞ", folding));
+        assert_eq!(Vec::<Range<usize>>::new(), find("は", "ぱ", EVERYTHING));
+    }
+
+    #[test]
+    fn vowel_signs_are_not_diacritics() {
+        let folding = Folding::default();
+        assert_eq!("किताब", folding.fold("किताब"));
+        assert_eq!(Vec::<Range<usize>>::new(), find("कतब", "किताब", folding));
+        assert_eq!(vec![0..15], find("किताब", "किताब", folding));
+    }
+
+    #[test]
+    fn ligatures_only_when_asked() {
+        assert_eq!(Vec::<Range<usize>>::new(), find("file", "a ﬁle", Folding::default()));
+        assert_eq!(vec![2..7], find("file", "a ﬁle", EVERYTHING));
+        assert_eq!(vec![2..5], find("fi", "a ﬁle", EVERYTHING));
+    }
+
+    #[test]
+    fn stripped_marks_stay_in_the_match() {
+        let searcher = FoldingSearcher::new("e", Folding::default());
+        assert_eq!(vec![1..6], searcher.find_all("xe\u{301}\u{323}y"));
+        assert_eq!(vec![2..3], searcher.find_all("\u{301}e"));
+    }
+
+    #[test]
+    fn whole_words_after_folding() {
+        let searcher = FoldingSearcher::new("cafe", Folding::default()).whole_word(true);
+        assert_eq!(vec![0..5], searcher.find_all("café cafés"));
+    }
+
+    #[test]
+    fn matches_fold_back() {
+        let alphabet = ["a", "e", "\u{e9}", "\u{301}", "ｶ", "ﾞ", "カ", "ガ", "Ａ", "A", "ﬁ", "f", "i"];
+        let mut random = Random::new(50);
+        for _ in 0..2000 {
+            let text = random.string(&alphabet, 12);
+            let pattern = random.string(&alphabet, 3);
+            for &folding in [Folding::default(), EVERYTHING].iter() {
+                let folded = Normalized::with_transform(&text, |piece| folding.fold(piece));
+                assert_eq!(folding.fold(&text), folded.as_str(), "{:?}", text);
+                let found = find(&pattern, &text, folding);
+                for range in found.iter() {
+                    let piece = folding.fold(&text[range.clone()]);
+                    assert!(piece.contains(&folding.fold(&pattern)),
+                            "{:?} in {:?} at {:?}", pattern, text, range);
+                }
+                assert_eq!(naive_count(&folding.fold(&pattern), folded.as_str()), found.len(),
+                           "{:?} in {:?}", pattern, text);
+            }
+        }
+    }
+}
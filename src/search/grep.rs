@@ -0,0 +1,221 @@
+//!
+//! Line-oriented search in the manner of `grep`: picks out the lines of a
+//! text that contain the pattern, or with `invert` the lines that do not,
+//! and prints each of them once together with the lines around them.
+//!
+//! Lines end at `\n`, `\r\n` or a lone `\r`, as in `LineIndex`. A line
+//! break at the very end of the text finishes the last line rather than
+//! starting an empty one.
+//!
+//! ```
+//! use boyer_moore::search::grep;
+//! use boyer_moore::search::lines::LineIndex;
+//! use boyer_moore::search::searcher::Searcher;
+//!
+//! let log = b"INFO start\nERROR disk\nINFO retry\nINFO done\n";
+//! let index = LineIndex::from_bytes(log);
+//! let searcher = Searcher::new("ERROR");
+//! assert_eq!(vec![2], grep::selected_lines(&searcher, log, &index, false));
+//! assert_eq!(vec![1, 3, 4], grep::selected_lines(&searcher, log, &index, true));
+//!
+//! let mut output = Vec::new();
+//! grep::write_lines(&mut output, log, &index, &[2], 0, 1).unwrap();
+//! assert_eq!(b"2:ERROR disk\n3-INFO retry\n".to_vec(), output);
+//! ```
+
+use std::cmp::min;
+use std::io;
+use std::io::Write;
+use std::ops::Range;
+
+use search::lines::LineIndex;
+use search::searcher::Searcher;
+
+///
+/// The number of lines in the text, not counting the empty one after a line
+/// break at the very end. An empty text has no lines.
+///
+pub fn line_count(text: &[u8], index: &LineIndex) -> usize {
+    return match text.last() {
+        None | Some(&b'\n') | Some(&b'\r') => index.line_count() - 1,
+        Some(_) => index.line_count(),
+    };
+}
+
+///
+/// Returns the lines, counting from 1, that contain a match of the searcher,
+/// or with `invert` the lines that contain none, in increasing order.
+/// # Arguments
+/// * `searcher` - the pattern, with any word and line conditions
+/// * `text` - the body to search in
+/// * `index` - the line index of `text`
+/// * `invert` - whether to select the lines without a match instead
+///
+pub fn selected_lines(searcher: &Searcher, text: &[u8], index: &LineIndex, invert: bool)
+                      -> Vec<usize> {
+    let mut matched = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = searcher.find_at(text, position) {
+        let line = index.line(offset);
+        matched.push(line);
+        // one match is enough, so the search goes on from the next line
+        position = index.line_range(line).unwrap().end;
+    }
+    if !invert {
+        return matched;
+    }
+    let mut matched = matched.into_iter().peekable();
+    return (1..line_count(text, index) + 1).filter(|&line| {
+        if matched.peek() == Some(&line) {
+            matched.next();
+            return false;
+        }
+        return true;
+    }).collect();
+}
+
+///
+/// Groups the selected lines with `before` lines of context ahead of each
+/// and `after` lines behind it. Groups that would touch or overlap are
+/// merged, so every line belongs to at most one group.
+/// # Arguments
+/// * `selected` - the selected lines, in increasing order
+/// * `before` - the number of lines to show ahead of each selected line
+/// * `after` - the number of lines to show behind each selected line
+/// * `line_count` - the number of lines in the text
+///
+/// # Result
+/// The ranges of lines of each group, counting from 1, in increasing order
+///
+pub fn context_groups(selected: &[usize], before: usize, after: usize, line_count: usize)
+                      -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for &line in selected {
+        let start = line.saturating_sub(before).max(1);
+        let end = min(line + after, line_count) + 1;
+        if let Some(last) = groups.last_mut() {
+            if start <= last.end {
+                last.end = last.end.max(end);
+                continue;
+            }
+        }
+        groups.push(start..end);
+    }
+    return groups;
+}
+
+///
+/// Writes the selected lines and their context, each as its line number,
+/// a `:` for a selected line or a `-` for a context line, and its contents.
+/// Groups that are not next to each other are separated by a `--` line.
+/// # Arguments
+/// * `writer` - where to write the lines to
+/// * `text` - the body that was searched
+/// * `index` - the line index of `text`
+/// * `selected` - the selected lines, in increasing order
+/// * `before` - the number of lines of context ahead of each selected line
+/// * `after` - the number of lines of context behind each selected line
+///
+pub fn write_lines<W: Write>(mut writer: W, text: &[u8], index: &LineIndex, selected: &[usize],
+                             before: usize, after: usize) -> io::Result<()> {
+    let groups = context_groups(selected, before, after, line_count(text, index));
+    let mut selected = selected.iter().peekable();
+    for (i, group) in groups.into_iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b"--\n")?;
+        }
+        for line in group {
+            let separator = if selected.peek() == Some(&&line) {
+                selected.next();
+                ':'
+            } else {
+                '-'
+            };
+            write!(writer, "{}{}", line, separator)?;
+            writer.write_all(line_contents(text, index, line))?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    return writer.flush();
+}
+
+/// The bytes of the line, counting from 1, without its line break.
+fn line_contents<'t>(text: &'t [u8], index: &LineIndex, line: usize) -> &'t [u8] {
+    let range = index.line_range(line).unwrap();
+    let mut contents = &text[range];
+    if contents.ends_with(b"\n") {
+        contents = &contents[..contents.len() - 1];
+    }
+    if contents.ends_with(b"\r") {
+        contents = &contents[..contents.len() - 1];
+    }
+    return contents;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(text: &str, selected: &[usize], before: usize, after: usize) -> String {
+        let mut output = Vec::new();
+        let index = LineIndex::new(text);
+        write_lines(&mut output, text.as_bytes(), &index, selected, before, after).unwrap();
+        return String::from_utf8(output).unwrap();
+    }
+
+    #[test]
+    fn counts_lines() {
+        assert_eq!(0, line_count(b"", &LineIndex::new("")));
+        assert_eq!(1, line_count(b"a", &LineIndex::new("a")));
+        assert_eq!(1, line_count(b"a\r\n", &LineIndex::new("a\r\n")));
+        assert_eq!(2, line_count(b"a\n\n", &LineIndex::new("a\n\n")));
+    }
+
+    #[test]
+    fn each_line_selected_once() {
+        let text = b"ab ab\nab\ncd\n";
+        let index = LineIndex::from_bytes(text);
+        let searcher = Searcher::new("ab");
+        assert_eq!(vec![1, 2], selected_lines(&searcher, text, &index, false));
+        assert_eq!(vec![3], selected_lines(&searcher, text, &index, true));
+    }
+
+    #[test]
+    fn invert_keeps_empty_lines() {
+        let text = b"x\n\nx\r\n";
+        let index = LineIndex::from_bytes(text);
+        let searcher = Searcher::new("x");
+        assert_eq!(vec![2], selected_lines(&searcher, text, &index, true));
+        assert_eq!(Vec::<usize>::new(), selected_lines(&searcher, b"", &LineIndex::new(""), true));
+    }
+
+    #[test]
+    fn invert_with_conditions() {
+        let text = b"test\nlatest\ntest case\n";
+        let index = LineIndex::from_bytes(text);
+        let searcher = Searcher::new("test").whole_line(true);
+        assert_eq!(vec![2, 3], selected_lines(&searcher, text, &index, true));
+    }
+
+    #[test]
+    fn groups_merge_when_touching() {
+        assert_eq!(vec![1..4, 6..9], context_groups(&[2, 7], 1, 1, 10));
+        assert_eq!(vec![1..4, 5..8], context_groups(&[2, 6], 1, 1, 10));
+        assert_eq!(vec![1..7], context_groups(&[2, 5], 1, 1, 10));
+        assert_eq!(vec![2..4], context_groups(&[2, 3], 0, 0, 10));
+        assert_eq!(vec![8..11], context_groups(&[10], 2, 3, 10));
+    }
+
+    #[test]
+    fn writes_separators() {
+        let text = "one\ntwo\nthree\nfour\nfive\nsix";
+        assert_eq!("1:one\n2-two\n--\n5-five\n6:six\n", write(text, &[1, 6], 1, 1));
+        assert_eq!("2:two\n3:three\n", write(text, &[2, 3], 0, 0));
+        assert_eq!("2:two\n--\n4:four\n", write(text, &[2, 4], 0, 0));
+    }
+
+    #[test]
+    fn strips_line_breaks() {
+        assert_eq!("1:a\n2:b\n3:c\n", write("a\r\nb\rc\n", &[1, 2, 3], 0, 0));
+    }
+}
@@ -0,0 +1,975 @@
+//!
+//! Indexes that follow the edits made to a buffer, such as an editor's,
+//! without going over the whole buffer again after each one.
+//!
+//! ```
+//! use boyer_moore::search::incremental::IncrementalSearch;
+//! use boyer_moore::search::searcher::Searcher;
+//!
+//! let mut search = IncrementalSearch::new(Searcher::new("fox"), "the fox, the box");
+//! search.apply_edit(13..14, "f");
+//! assert_eq!(vec![4, 13], search.matches().collect::<Vec<usize>>());
+//! ```
+//!
+//! ```
+//! use boyer_moore::search::incremental::IncrementalLineIndex;
+//!
+//! let mut lines = IncrementalLineIndex::new("one\ntwo\nthree");
+//! lines.apply_edit(3..4, " and ");
+//! assert_eq!(2, lines.line_count());
+//! assert_eq!(Some(0..12), lines.line_range(1));
+//! assert_eq!(2, lines.line(12));
+//! ```
+//!
+//! Both are balanced trees of lengths laid end to end, built on the AVL
+//! rebalancing of `tree::Balanced` rather than on `tree::Tree`. A `Tree` is
+//! keyed by absolute offsets, every one of which after an edit would have to
+//! change; here a node holds its distance from the one before, so an edit
+//! changes a single length and moves everything after it.
+
+use std::cmp::max;
+use std::cmp::min;
+use std::ops::Range;
+
+use search::search::MatchKind;
+use search::searcher::Searcher;
+use search::tree::Balanced;
+use search::tree::height;
+use search::tree::rebalance;
+use search::tree::remove_min;
+
+impl IncrementalLineIndex {
+
+    ///
+    /// Indexes the lines of the text.
+    ///
+    pub fn new(text: &str) -> IncrementalLineIndex {
+        return IncrementalLineIndex::from_bytes(text.as_bytes());
+    }
+
+    ///
+    /// Indexes the lines of the text.
+    ///
+    pub fn from_bytes(text: &[u8]) -> IncrementalLineIndex {
+        let mut splitter = LineSplitter::new();
+        splitter.text(text);
+        let (mut lines, rest) = splitter.finish();
+        lines.push((rest, LineBreak::None));
+        let new_node = |(len, line_break)| LineNode::new(len, line_break);
+        return IncrementalLineIndex { root: build(&lines, &new_node) };
+    }
+
+    ///
+    /// The number of lines. A text ending in a line break has an empty last
+    /// line, and an empty text has a single empty line.
+    ///
+    pub fn line_count(&self) -> usize {
+        return count(&self.root);
+    }
+
+    ///
+    /// The length of the text in bytes.
+    ///
+    pub fn len(&self) -> usize {
+        return total(&self.root);
+    }
+
+    ///
+    /// Whether the text is empty. It still has a single empty line.
+    ///
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    ///
+    /// The line containing the byte offset, counting from 1. A line break
+    /// belongs to the line it terminates, and offsets past the end of the
+    /// text belong to the last line.
+    ///
+    pub fn line(&self, mut offset: usize) -> usize {
+        let mut line = 1;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            let left = total(&n.left);
+            if offset < left {
+                node = &n.left;
+            } else if offset < left + n.len {
+                return line + count(&n.left);
+            } else {
+                offset -= left + n.len;
+                line += count(&n.left) + 1;
+                node = &n.right;
+            }
+        }
+        return self.line_count();
+    }
+
+    ///
+    /// The byte offset of the first character of the line, counting lines
+    /// from 1.
+    ///
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        if line == 0 || line > self.line_count() {
+            return None;
+        }
+        let mut index = line - 1;
+        let mut start = 0;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            let left = count(&n.left);
+            if index < left {
+                node = &n.left;
+            } else if index == left {
+                return Some(start + total(&n.left));
+            } else {
+                index -= left + 1;
+                start += total(&n.left) + n.len;
+                node = &n.right;
+            }
+        }
+        return None;
+    }
+
+    ///
+    /// The bytes spanned by the line, counting lines from 1, including its
+    /// line break if it has one.
+    ///
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = match self.line_start(line) {
+            Some(start) => start,
+            None => return None,
+        };
+        let end = self.line_start(line + 1).unwrap_or(self.len());
+        return Some(start..end);
+    }
+
+    ///
+    /// Replaces the bytes in the range with the new text. Only the lines the
+    /// edit touches are looked at again; the offsets of the lines after them
+    /// move with the edit without being visited.
+    ///
+    /// # Panics
+    /// If the range is reversed or goes past the end of the text.
+    ///
+    pub fn apply_edit<T: AsRef<[u8]> + ?Sized>(&mut self, range: Range<usize>, new_text: &T) {
+        assert!(range.start <= range.end, "edit range is reversed");
+        assert!(range.end <= self.len(), "edit range is past the end of the text");
+
+        // the lines holding the edit, plus the one before when the edit starts
+        // a line, since a `\r` ending it may pair up with a `\n` at the start
+        let mut first = self.line(range.start) - 1;
+        if first > 0 && self.line_start(first + 1) == Some(range.start) {
+            first -= 1;
+        }
+        let window_start = self.line_start(first + 1).unwrap();
+        let last = self.line(range.end) - 1;
+        let had_next = last + 1 < self.line_count();
+
+        let mut old = Vec::new();
+        for _ in first..last + 1 {
+            let node = remove_at(&mut self.root, first);
+            old.push((node.len, node.line_break));
+        }
+
+        // the window's old lines are only known by their lengths and line
+        // breaks, which is all that is needed to split the new window again
+        let mut splitter = LineSplitter::new();
+        emit_lines(&mut splitter, &old, window_start, window_start..range.start);
+        splitter.text(new_text.as_ref());
+        emit_lines(&mut splitter, &old, window_start, range.end..usize::MAX);
+        let (lines, rest) = splitter.finish();
+
+        let mut index = first;
+        for (len, line_break) in lines {
+            insert_at(&mut self.root, index, Box::new(LineNode::new(len, line_break)));
+            index += 1;
+        }
+        if had_next {
+            // the window ended in a line break, or the edit removed it and the
+            // rest joins the line that came next
+            if rest > 0 {
+                let next = remove_at(&mut self.root, index);
+                insert_at(&mut self.root, index,
+                          Box::new(LineNode::new(next.len + rest, next.line_break)));
+            }
+        } else {
+            insert_at(&mut self.root, index, Box::new(LineNode::new(rest, LineBreak::None)));
+        }
+    }
+}
+
+///
+/// The lines of a text that is being edited, kept as a balanced tree of
+/// line lengths. Every node also caches the number of lines and bytes in its
+/// subtree, so an edit that changes the length of a line moves the start of
+/// all the lines after it without touching them.
+///
+#[derive(Clone)]
+pub struct IncrementalLineIndex {
+    root: LineLink,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineBreak {
+    None,
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineBreak {
+    fn bytes(&self) -> &'static [u8] {
+        return match *self {
+            LineBreak::None => b"",
+            LineBreak::Lf => b"\n",
+            LineBreak::CrLf => b"\r\n",
+            LineBreak::Cr => b"\r",
+        };
+    }
+}
+
+type LineLink = Option<Box<LineNode>>;
+
+#[derive(Clone)]
+struct LineNode {
+    /// length of the line in bytes, including its line break
+    len: usize,
+    line_break: LineBreak,
+    height: usize,
+    count: usize,
+    total: usize,
+    left: LineLink,
+    right: LineLink,
+}
+
+impl LineNode {
+    fn new(len: usize, line_break: LineBreak) -> LineNode {
+        return LineNode { len: len, line_break: line_break, height: 1, count: 1, total: len,
+                          left: None, right: None };
+    }
+}
+
+impl Balanced for LineNode {
+    fn left(&self) -> &LineLink {
+        return &self.left;
+    }
+
+    fn right(&self) -> &LineLink {
+        return &self.right;
+    }
+
+    fn left_mut(&mut self) -> &mut LineLink {
+        return &mut self.left;
+    }
+
+    fn right_mut(&mut self) -> &mut LineLink {
+        return &mut self.right;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    /// Recomputes the cached height, line count and length after either
+    /// child has changed.
+    fn update(&mut self) {
+        self.height = 1 + max(height(&self.left), height(&self.right));
+        self.count = 1 + count(&self.left) + count(&self.right);
+        self.total = self.len + total(&self.left) + total(&self.right);
+    }
+}
+
+impl Lengths for LineNode {
+    fn count(&self) -> usize {
+        return self.count;
+    }
+
+    fn total(&self) -> usize {
+        return self.total;
+    }
+}
+
+///
+/// A node of a balanced tree of lengths laid end to end, which caches how
+/// many nodes its subtree holds and the sum of their lengths, so that nodes
+/// can be found by their index or by an offset.
+///
+trait Lengths: Balanced {
+    fn count(&self) -> usize;
+    fn total(&self) -> usize;
+}
+
+fn count<N: Lengths>(link: &Option<Box<N>>) -> usize {
+    return match *link {
+        Some(ref node) => node.count(),
+        None => 0,
+    };
+}
+
+fn total<N: Lengths>(link: &Option<Box<N>>) -> usize {
+    return match *link {
+        Some(ref node) => node.total(),
+        None => 0,
+    };
+}
+
+///
+/// Builds a balanced tree holding a node for each of the items, in order.
+///
+fn build<T: Copy, N: Lengths, F: Fn(T) -> N>(items: &[T], new_node: &F) -> Option<Box<N>> {
+    if items.is_empty() {
+        return None;
+    }
+    let middle = items.len() / 2;
+    let mut node = Box::new(new_node(items[middle]));
+    *node.left_mut() = build(&items[..middle], new_node);
+    *node.right_mut() = build(&items[middle + 1..], new_node);
+    node.update();
+    return Some(node);
+}
+
+///
+/// Inserts the node so that it becomes the one at the index, counting
+/// from 0.
+///
+fn insert_at<N: Lengths>(link: &mut Option<Box<N>>, index: usize, node: Box<N>) {
+    match *link {
+        None => {
+            *link = Some(node);
+            return;
+        },
+        Some(ref mut n) => {
+            let left = count(n.left());
+            if index <= left {
+                insert_at(n.left_mut(), index, node);
+            } else {
+                insert_at(n.right_mut(), index - left - 1, node);
+            }
+        },
+    }
+    rebalance(link);
+}
+
+///
+/// Detaches the node at the index, counting from 0.
+///
+fn remove_at<N: Lengths>(link: &mut Option<Box<N>>, index: usize) -> Box<N> {
+    let left = count(link.as_ref().expect("index out of range").left());
+    let removed = if index < left {
+        remove_at(link.as_mut().unwrap().left_mut(), index)
+    } else if index > left {
+        remove_at(link.as_mut().unwrap().right_mut(), index - left - 1)
+    } else {
+        let mut removed = link.take().unwrap();
+        *link = match (removed.left_mut().take(), removed.right_mut().take()) {
+            (None, right) => right,
+            (left, None) => left,
+            (left, right) => {
+                let mut right = right;
+                let mut successor = remove_min(&mut right);
+                *successor.left_mut() = left;
+                *successor.right_mut() = right;
+                Some(successor)
+            },
+        };
+        removed
+    };
+    rebalance(link);
+    return removed;
+}
+
+///
+/// Feeds the part of the lines that falls in the range to the splitter. The
+/// lines start at `start`, and everything in them but their line breaks is
+/// known not to hold any line break.
+///
+fn emit_lines(splitter: &mut LineSplitter, lines: &[(usize, LineBreak)], mut start: usize,
+              range: Range<usize>) {
+    for &(len, line_break) in lines {
+        let breaks = line_break.bytes();
+        let content_end = start + len - breaks.len();
+        let from = max(start, range.start);
+        if content_end > from && range.end > from {
+            splitter.content(content_end.min(range.end) - from);
+        }
+        for (i, byte) in breaks.iter().enumerate() {
+            let offset = content_end + i;
+            if offset >= range.start && offset < range.end {
+                splitter.byte(*byte);
+            }
+        }
+        start += len;
+    }
+}
+
+///
+/// Splits a stream of bytes into lines, where runs of bytes holding no line
+/// breaks can be given by their length alone.
+///
+struct LineSplitter {
+    lines: Vec<(usize, LineBreak)>,
+    current: usize,
+    pending_cr: bool,
+}
+
+impl LineSplitter {
+    fn new() -> LineSplitter {
+        return LineSplitter { lines: Vec::new(), current: 0, pending_cr: false };
+    }
+
+    fn end_line(&mut self, line_break: LineBreak) {
+        self.lines.push((self.current + line_break.bytes().len(), line_break));
+        self.current = 0;
+    }
+
+    /// A `\r` ends its line on its own unless a `\n` comes right after it.
+    fn flush_cr(&mut self) {
+        if self.pending_cr {
+            self.pending_cr = false;
+            self.end_line(LineBreak::Cr);
+        }
+    }
+
+    fn content(&mut self, len: usize) {
+        if len > 0 {
+            self.flush_cr();
+            self.current += len;
+        }
+    }
+
+    fn byte(&mut self, byte: u8) {
+        match byte {
+            b'\n' if self.pending_cr => {
+                self.pending_cr = false;
+                self.end_line(LineBreak::CrLf);
+            },
+            b'\n' => self.end_line(LineBreak::Lf),
+            b'\r' => {
+                self.flush_cr();
+                self.pending_cr = true;
+            },
+            _ => self.content(1),
+        }
+    }
+
+    fn text(&mut self, text: &[u8]) {
+        let mut run = 0;
+        for byte in text {
+            if *byte == b'\n' || *byte == b'\r' {
+                self.content(run);
+                run = 0;
+                self.byte(*byte);
+            } else {
+                run += 1;
+            }
+        }
+        self.content(run);
+    }
+
+    /// The complete lines, and the length of the unfinished one at the end.
+    fn finish(mut self) -> (Vec<(usize, LineBreak)>, usize) {
+        self.flush_cr();
+        return (self.lines, self.current);
+    }
+}
+
+impl IncrementalSearch {
+
+    ///
+    /// Searches the text for the first time.
+    ///
+    pub fn new<T: AsRef<[u8]> + ?Sized>(searcher: Searcher, text: &T) -> IncrementalSearch {
+        let text = text.as_ref().to_vec();
+        let mut previous = 0;
+        let gaps: Vec<usize> = searcher.find_iter(&text).map(|offset| {
+            let gap = offset - previous;
+            previous = offset;
+            gap
+        }).collect();
+        let matches = build(&gaps, &MatchNode::new);
+        return IncrementalSearch { searcher: searcher, text: text, matches: matches };
+    }
+
+    /// The text as of the last edit.
+    pub fn text(&self) -> &[u8] {
+        return &self.text;
+    }
+
+    ///
+    /// The offsets of the matches in the current text, in increasing order,
+    /// overlapping each other or not depending on the searcher's match kind.
+    ///
+    pub fn matches(&self) -> Matches<'_> {
+        let mut matches = Matches { stack: Vec::new(), offset: 0 };
+        matches.push_left(&self.matches);
+        return matches;
+    }
+
+    pub fn match_count(&self) -> usize {
+        return count(&self.matches);
+    }
+
+    ///
+    /// Replaces the bytes in the range with the new text and brings the
+    /// matches up to date. Only the edited region, widened on each side by
+    /// one byte less than the pattern and by the bytes the word and line
+    /// conditions look at, is searched again; the matches after it move by
+    /// the change in length without being visited.
+    ///
+    /// Without overlaps, which matches are kept depends on the ones before
+    /// them, so the search goes on past the edited region until it lands on
+    /// a match that was already known, never looking past that match. When
+    /// the edit changes which of a run of overlapping occurrences are kept,
+    /// as inserting an "a" into "aaaa…" does for "aa", every later match
+    /// changes with it, and the edit costs O(n) in the worst case.
+    ///
+    /// # Panics
+    /// If the range is reversed or goes past the end of the text.
+    ///
+    pub fn apply_edit<T: AsRef<[u8]> + ?Sized>(&mut self, range: Range<usize>, new_text: &T) {
+        let new_text = new_text.as_ref();
+        assert!(range.start <= range.end, "edit range is reversed");
+        assert!(range.end <= self.text.len(), "edit range is past the end of the text");
+
+        let _ = self.text.splice(range.clone(), new_text.iter().cloned());
+        let m = self.searcher.pattern().len();
+        if m == 0 {
+            return;
+        }
+        let c = self.searcher.context_len();
+        let edit_end = range.start + new_text.len();
+
+        // take out every match that, with the bytes its conditions look at,
+        // reaches into the edit; the first match after it then moves with the
+        // change in length, and the others with it
+        let window_start = (range.start + 1).saturating_sub(m + c);
+        let first = matches_before(&self.matches, window_start);
+        for _ in first..matches_before(&self.matches, range.end + c) {
+            remove_match(&mut self.matches, first);
+        }
+        if first < count(&self.matches) {
+            change_gap(&mut self.matches, first, |gap| gap + edit_end - range.end);
+        }
+
+        match self.searcher.kind() {
+            MatchKind::Overlapping => {
+                // the text is searched whole so that the conditions see past the
+                // window, but no further than the last match starting in it needs
+                let window_end = edit_end + c;
+                let haystack = &self.text[..min(window_end + m - 1 + c, self.text.len())];
+                let mut position = window_start;
+                while let Some(found) = self.searcher.find_at(haystack, position) {
+                    if found >= window_end {
+                        break;
+                    }
+                    insert_match(&mut self.matches, found);
+                    position = found + 1;
+                }
+            },
+            MatchKind::NonOverlapping => {
+                // the index of the first match after the edit not yet confirmed
+                let mut next = first;
+                let mut position = match first {
+                    0 => window_start,
+                    _ => max(offset_at(&self.matches, first - 1) + m, window_start),
+                };
+                // an occurrence past the edit that was not a match was hidden by an
+                // old match overlapping it, so only the removed ones can uncover it
+                let mut hidden_until = edit_end + c + m;
+                loop {
+                    // the old matches the search went past are no longer matches
+                    while next < count(&self.matches) && offset_at(&self.matches, next) < position {
+                        hidden_until = max(hidden_until, offset_at(&self.matches, next) + m);
+                        remove_match(&mut self.matches, next);
+                    }
+                    // the search finds the next old match if nothing comes before it,
+                    // and without one no match starts past what the removed ones hid
+                    let known = next < count(&self.matches);
+                    let bound = if known {
+                        offset_at(&self.matches, next) + m
+                    } else {
+                        hidden_until + m
+                    };
+                    let haystack = &self.text[..min(bound + c, self.text.len())];
+                    let found = match self.searcher.find_at(haystack, position) {
+                        // past the bound, the conditions may not see all they look at
+                        Some(found) if found + m <= bound => found,
+                        _ => return,
+                    };
+                    if known && offset_at(&self.matches, next) == found {
+                        // the text from here on is unchanged, and so are the matches
+                        return;
+                    }
+                    insert_match(&mut self.matches, found);
+                    next += 1;
+                    position = found + m;
+                }
+            },
+        }
+    }
+}
+
+///
+/// The matches of a searcher in a text that is being edited, kept up to date
+/// by searching only around each edit.
+///
+#[derive(Clone)]
+pub struct IncrementalSearch {
+    searcher: Searcher,
+    text: Vec<u8>,
+    matches: MatchLink,
+}
+
+type MatchLink = Option<Box<MatchNode>>;
+
+///
+/// A match in the balanced tree of matches, which holds the distances
+/// between them rather than their offsets, so that an edit moves all the
+/// matches after it by changing a single distance.
+///
+#[derive(Clone)]
+struct MatchNode {
+    /// distance from the match before, or from the start of the text
+    gap: usize,
+    height: usize,
+    count: usize,
+    total: usize,
+    left: MatchLink,
+    right: MatchLink,
+}
+
+impl MatchNode {
+    fn new(gap: usize) -> MatchNode {
+        return MatchNode { gap: gap, height: 1, count: 1, total: gap, left: None, right: None };
+    }
+}
+
+impl Balanced for MatchNode {
+    fn left(&self) -> &MatchLink {
+        return &self.left;
+    }
+
+    fn right(&self) -> &MatchLink {
+        return &self.right;
+    }
+
+    fn left_mut(&mut self) -> &mut MatchLink {
+        return &mut self.left;
+    }
+
+    fn right_mut(&mut self) -> &mut MatchLink {
+        return &mut self.right;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    fn update(&mut self) {
+        self.height = 1 + max(height(&self.left), height(&self.right));
+        self.count = 1 + count(&self.left) + count(&self.right);
+        self.total = self.gap + total(&self.left) + total(&self.right);
+    }
+}
+
+impl Lengths for MatchNode {
+    fn count(&self) -> usize {
+        return self.count;
+    }
+
+    fn total(&self) -> usize {
+        return self.total;
+    }
+}
+
+///
+/// The offset of the match at the index, counting from 0.
+///
+fn offset_at(mut link: &MatchLink, mut index: usize) -> usize {
+    let mut offset = 0;
+    while let Some(ref n) = *link {
+        let left = count(&n.left);
+        if index < left {
+            link = &n.left;
+        } else {
+            offset += total(&n.left) + n.gap;
+            if index == left {
+                return offset;
+            }
+            index -= left + 1;
+            link = &n.right;
+        }
+    }
+    panic!("match index out of range");
+}
+
+///
+/// The number of matches before the offset.
+///
+fn matches_before(mut link: &MatchLink, offset: usize) -> usize {
+    let mut before = 0;
+    let mut start = 0;
+    while let Some(ref n) = *link {
+        let here = start + total(&n.left) + n.gap;
+        if here < offset {
+            before += count(&n.left) + 1;
+            start = here;
+            link = &n.right;
+        } else {
+            link = &n.left;
+        }
+    }
+    return before;
+}
+
+///
+/// Replaces the distance of the match at the index from the one before it,
+/// which moves it and every match after it.
+///
+fn change_gap<F: FnOnce(usize) -> usize>(link: &mut MatchLink, index: usize, change: F) {
+    let n = link.as_mut().expect("match index out of range");
+    let left = count(&n.left);
+    if index < left {
+        change_gap(&mut n.left, index, change);
+    } else if index > left {
+        change_gap(&mut n.right, index - left - 1, change);
+    } else {
+        n.gap = change(n.gap);
+    }
+    n.update();
+}
+
+///
+/// Adds a match at an offset that is not one already, leaving the others
+/// where they are.
+///
+fn insert_match(root: &mut MatchLink, offset: usize) {
+    let index = matches_before(root, offset);
+    let previous = if index > 0 { offset_at(root, index - 1) } else { 0 };
+    if index < count(root) {
+        change_gap(root, index, |gap| gap - (offset - previous));
+    }
+    insert_at(root, index, Box::new(MatchNode::new(offset - previous)));
+}
+
+///
+/// Removes the match at the index, counting from 0, leaving the others where
+/// they are.
+///
+fn remove_match(root: &mut MatchLink, index: usize) {
+    let gap = remove_at(root, index).gap;
+    if index < count(root) {
+        change_gap(root, index, |next| next + gap);
+    }
+}
+
+///
+/// Iterator over the offsets of the matches of an `IncrementalSearch`, in
+/// increasing order.
+///
+pub struct Matches<'a> {
+    stack: Vec<&'a MatchNode>,
+    /// the offset of the match returned last
+    offset: usize,
+}
+
+impl<'a> Matches<'a> {
+    fn push_left(&mut self, mut link: &'a MatchLink) {
+        while let Some(ref node) = *link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        self.offset += node.gap;
+        return Some(self.offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::lines::LineIndex;
+    use search::test_util::Random;
+
+    /// Checks the index against one rebuilt from scratch.
+    fn assert_same_lines(text: &[u8], index: &IncrementalLineIndex) {
+        let expected = LineIndex::from_bytes(text);
+        assert_eq!(text.len(), index.len(), "{:?}", text);
+        assert_eq!(expected.line_count(), index.line_count(), "{:?}", text);
+        for line in 0..expected.line_count() + 2 {
+            assert_eq!(expected.line_range(line), index.line_range(line), "line {} of {:?}",
+                       line, text);
+        }
+        for offset in 0..text.len() + 2 {
+            assert_eq!(expected.line(offset), index.line(offset), "offset {} of {:?}",
+                       offset, text);
+        }
+    }
+
+    #[test]
+    fn empty_text() {
+        let mut index = IncrementalLineIndex::new("");
+        assert_same_lines(b"", &index);
+        assert!(index.is_empty());
+        index.apply_edit(0..0, "\n");
+        assert!(!index.is_empty());
+    }
+
+    #[test]
+    fn insert_line_breaks() {
+        let mut index = IncrementalLineIndex::new("ab");
+        index.apply_edit(1..1, "\n\n");
+        assert_same_lines(b"a\n\nb", &index);
+        index.apply_edit(4..4, "\n");
+        assert_same_lines(b"a\n\nb\n", &index);
+    }
+
+    #[test]
+    fn join_cr_and_lf() {
+        let mut index = IncrementalLineIndex::new("a\rb\nc");
+        index.apply_edit(2..3, "");
+        assert_same_lines(b"a\r\nc", &index);
+        index.apply_edit(2..2, "x");
+        assert_same_lines(b"a\rx\nc", &index);
+    }
+
+    #[test]
+    fn split_cr_lf() {
+        let mut index = IncrementalLineIndex::new("a\r\nb");
+        index.apply_edit(2..2, "x");
+        assert_same_lines(b"a\rx\nb", &index);
+    }
+
+    #[test]
+    fn delete_everything() {
+        let mut index = IncrementalLineIndex::new("a\nb\r\nc\r");
+        index.apply_edit(0..7, "");
+        assert_same_lines(b"", &index);
+    }
+
+    #[test]
+    #[should_panic]
+    fn edit_past_the_end() {
+        IncrementalLineIndex::new("abc").apply_edit(2..4, "");
+    }
+
+    #[test]
+    fn matches_rebuild_after_random_edits() {
+        let mut random = Random::new(41);
+        for _ in 0..200 {
+            let mut text = random.bytes(b"ab\n\r", 20);
+            let mut index = IncrementalLineIndex::from_bytes(&text);
+            for _ in 0..20 {
+                let start = random.next(text.len() + 1);
+                let end = start + random.next(text.len() - start + 1);
+                let new_text = random.bytes(b"ab\n\r", 5);
+                let mut edited = text[..start].to_vec();
+                edited.extend_from_slice(&new_text);
+                edited.extend_from_slice(&text[end..]);
+
+                index.apply_edit(start..end, &new_text);
+                text = edited;
+                assert_same_lines(&text, &index);
+            }
+        }
+    }
+
+    #[test]
+    fn search_edit_creates_and_breaks_matches() {
+        let mut search = IncrementalSearch::new(Searcher::new("abc"), "abc xbc abc");
+        search.apply_edit(4..5, "a");
+        assert_eq!(vec![0, 4, 8], search.matches().collect::<Vec<usize>>());
+        search.apply_edit(1..2, "");
+        assert_eq!(vec![3, 7], search.matches().collect::<Vec<usize>>());
+        assert_eq!(b"ac abc abc", search.text());
+        assert_eq!(2, search.match_count());
+    }
+
+    #[test]
+    fn search_edit_resyncs_non_overlapping_matches() {
+        let mut search = IncrementalSearch::new(Searcher::new("aa"), "aaaaaaa");
+        search.apply_edit(0..0, "a");
+        assert_eq!(vec![0, 2, 4, 6], search.matches().collect::<Vec<usize>>());
+        search.apply_edit(0..1, "");
+        assert_eq!(vec![0, 2, 4], search.matches().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn search_edit_next_to_a_whole_word() {
+        for &kind in [MatchKind::Overlapping, MatchKind::NonOverlapping].iter() {
+            let searcher = Searcher::new("ab").whole_word(true).match_kind(kind);
+            let edits = [("ab ab ab", 5..6, "z", vec![0]), ("xxaq", 3..4, "b", vec![]),
+                         ("q ab", 1..2, "x", vec![]), ("ab.x", 3..4, "", vec![0])];
+            for &(text, ref range, new_text, ref expected) in edits.iter() {
+                let mut search = IncrementalSearch::new(searcher.clone(), text);
+                search.apply_edit(range.clone(), new_text);
+                assert_eq!(*expected, search.matches().collect::<Vec<usize>>(),
+                           "{:?} in {:?}", range, text);
+            }
+        }
+    }
+
+    #[test]
+    fn search_matches_full_rescan_after_random_edits() {
+        let mut random = Random::new(42);
+        let kinds = [MatchKind::Overlapping, MatchKind::NonOverlapping];
+        let alphabet = ["a", "b", "\n", "\r", " ", "\u{e9}"];
+        for round in 0..600 {
+            let pattern = ["a", "ab", "aa", "aba", "aab", "a\nb"][random.next(6)];
+            let searcher = Searcher::new(pattern).match_kind(kinds[round % 2])
+                .whole_word(random.next(3) == 0).whole_line(random.next(3) == 0);
+            let mut text = random.string(&alphabet, 20).into_bytes();
+            let mut search = IncrementalSearch::new(searcher.clone(), &text);
+            for _ in 0..20 {
+                let start = random.next(text.len() + 1);
+                let end = start + random.next(text.len() - start + 1);
+                let new_text = random.string(&alphabet, 4).into_bytes();
+                let mut edited = text[..start].to_vec();
+                edited.extend_from_slice(&new_text);
+                edited.extend_from_slice(&text[end..]);
+
+                search.apply_edit(start..end, &new_text);
+                text = edited;
+                assert_eq!(&text[..], search.text());
+                assert_eq!(searcher.find_iter(&text).collect::<Vec<usize>>(),
+                           search.matches().collect::<Vec<usize>>(),
+                           "{:?} in {:?}", pattern, text);
+            }
+        }
+    }
+
+    #[test]
+    fn search_edit_moves_later_matches() {
+        let text = "ab".repeat(50000);
+        for &kind in [MatchKind::Overlapping, MatchKind::NonOverlapping].iter() {
+            let mut search = IncrementalSearch::new(Searcher::new("ab").match_kind(kind), &text);
+            search.apply_edit(1..1, "x");
+            search.apply_edit(0..0, "ab");
+            let matches: Vec<usize> = search.matches().collect();
+            assert_eq!(50000, matches.len());
+            assert_eq!(&[0, 5, 7], &matches[..3]);
+            assert_eq!(Some(&100001), matches.last());
+            assert!(height(&search.matches) <= 25);
+        }
+    }
+
+    #[test]
+    fn many_lines_stay_balanced() {
+        let mut index = IncrementalLineIndex::new("");
+        for i in 0..100000 {
+            let end = index.len();
+            index.apply_edit(end..end, if i % 2 == 0 { "line\n" } else { "\r\n" });
+        }
+        assert_eq!(100001, index.line_count());
+        assert!(height(&index.root) <= 25);
+        assert_eq!(Some(245000..245005), index.line_range(100001 - 30000));
+    }
+}
@@ -0,0 +1,429 @@
+//!
+//! An interval tree of half-open `start..end` spans, such as the matches of
+//! several patterns, answering which spans overlap a range or contain a point.
+//!
+//! ```
+//! use boyer_moore::search::interval::IntervalTree;
+//!
+//! let mut spans = IntervalTree::new();
+//! spans.insert(0..4, "test");
+//! spans.insert(2..6, "strike");
+//! spans.insert(9..12, "foo");
+//!
+//! let hits: Vec<_> = spans.overlapping(3..10).map(|(span, name)| (span, *name)).collect();
+//! assert_eq!(vec![(0..4, "test"), (2..6, "strike"), (9..12, "foo")], hits);
+//! assert_eq!(1, spans.stabbing(5).count());
+//! ```
+
+use std::cmp::max;
+use std::cmp::Ordering;
+use std::ops::Bound;
+use std::ops::Range;
+
+use search::tree::Balanced;
+use search::tree::height;
+use search::tree::rebalance;
+use search::tree::remove_min;
+
+impl<T: Ord + Copy, V> IntervalTree<T, V> {
+
+    pub fn new() -> IntervalTree<T, V> {
+        return IntervalTree { root: None, size: 0 };
+    }
+
+    pub fn size(&self) -> usize {
+        return self.size;
+    }
+
+    ///
+    /// Inserts the span, returning whether it was already present, in which
+    /// case only its value is replaced.
+    ///
+    /// # Panics
+    /// If the span is empty.
+    ///
+    pub fn insert(&mut self, span: Range<T>, value: V) -> bool {
+        assert!(span.start < span.end, "empty interval");
+        let result = IntervalTree::insert_node(&mut self.root, span, value);
+        if !result {
+            self.size += 1;
+        }
+        return result;
+    }
+
+    ///
+    /// Removes the span, returning its value if it was present.
+    ///
+    pub fn remove(&mut self, span: &Range<T>) -> Option<V> {
+        let result = IntervalTree::remove_node(&mut self.root, span);
+        if result.is_some() {
+            self.size -= 1;
+        }
+        return result;
+    }
+
+    ///
+    /// Removes every span from the tree.
+    ///
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.size = 0;
+    }
+
+    ///
+    /// Iterates over the spans sharing at least one position with the range,
+    /// ordered by start and then by end.
+    ///
+    /// ```
+    /// use boyer_moore::search::interval::IntervalTree;
+    ///
+    /// let mut spans = IntervalTree::new();
+    /// spans.insert(0..4, ());
+    /// spans.insert(4..8, ());
+    /// assert_eq!(vec![0..4], spans.overlapping(2..4).map(|(span, _)| span).collect::<Vec<_>>());
+    /// assert_eq!(0, spans.overlapping(2..2).count());
+    /// ```
+    ///
+    pub fn overlapping(&self, range: Range<T>) -> Overlapping<'_, T, V> {
+        let mut result = Overlapping { stack: Vec::new(), after: Some(range.start),
+                                       before: Bound::Excluded(range.end) };
+        if range.start < range.end {
+            result.push_left(&self.root);
+        }
+        return result;
+    }
+
+    ///
+    /// Iterates over the spans containing the point, ordered by start and then
+    /// by end.
+    ///
+    pub fn stabbing(&self, point: T) -> Overlapping<'_, T, V> {
+        let mut result = Overlapping { stack: Vec::new(), after: Some(point),
+                                       before: Bound::Included(point) };
+        result.push_left(&self.root);
+        return result;
+    }
+
+    ///
+    /// Iterates over all spans, ordered by start and then by end.
+    ///
+    pub fn iter(&self) -> Overlapping<'_, T, V> {
+        let mut result = Overlapping { stack: Vec::new(), after: None, before: Bound::Unbounded };
+        result.push_left(&self.root);
+        return result;
+    }
+
+    fn remove_node(node: &mut Link<T, V>, span: &Range<T>) -> Option<V> {
+        let ordering = match *node {
+            None => return None,
+            Some(ref n) => (span.start, span.end).cmp(&(n.start, n.end)),
+        };
+
+        let removed = match ordering {
+            Ordering::Less => IntervalTree::remove_node(&mut node.as_mut().unwrap().left, span),
+            Ordering::Greater => IntervalTree::remove_node(&mut node.as_mut().unwrap().right, span),
+            Ordering::Equal => {
+                let mut removed = node.take().unwrap();
+                *node = match (removed.left.take(), removed.right.take()) {
+                    (None, right) => right,
+                    (left, None) => left,
+                    (left, right) => {
+                        let mut right = right;
+                        let mut successor = remove_min(&mut right);
+                        successor.left = left;
+                        successor.right = right;
+                        Some(successor)
+                    },
+                };
+                Some(removed.value)
+            },
+        };
+        rebalance(node);
+        return removed;
+    }
+
+    fn insert_node(node: &mut Link<T, V>, span: Range<T>, value: V) -> bool {
+        let result = match *node {
+            None => {
+                *node = Some(Box::new(IntervalNode::new(span, value)));
+                return false;
+            },
+            Some(ref mut node) => {
+                match (span.start, span.end).cmp(&(node.start, node.end)) {
+                    Ordering::Less => IntervalTree::insert_node(&mut node.left, span, value),
+                    Ordering::Greater => IntervalTree::insert_node(&mut node.right, span, value),
+                    Ordering::Equal => {
+                        node.value = value;
+                        return true;
+                    },
+                }
+            },
+        };
+        rebalance(node);
+        return result;
+    }
+}
+
+impl<T: Ord + Copy, V> Default for IntervalTree<T, V> {
+    fn default() -> IntervalTree<T, V> {
+        return IntervalTree::new();
+    }
+}
+
+type Link<T, V> = Option<Box<IntervalNode<T, V>>>;
+
+///
+/// A set of half-open spans with a value for each, kept balanced as an AVL
+/// tree ordered by start and then by end. Each node also caches the largest
+/// end in its subtree, so queries skip subtrees that end before the range.
+///
+#[derive(Clone)]
+pub struct IntervalTree<T: Ord + Copy, V> {
+    root: Link<T, V>,
+    size: usize,
+}
+
+#[derive(Clone)]
+struct IntervalNode<T, V> {
+    start: T,
+    end: T,
+    value: V,
+    height: usize,
+    max_end: T,
+    left: Link<T, V>,
+    right: Link<T, V>,
+}
+
+impl<T: Ord + Copy, V> IntervalNode<T, V> {
+    fn new(span: Range<T>, value: V) -> IntervalNode<T, V> {
+        return IntervalNode { start: span.start, end: span.end, value: value, height: 1,
+                              max_end: span.end, left: None, right: None };
+    }
+}
+
+impl<T: Ord + Copy, V> Balanced for IntervalNode<T, V> {
+    fn left(&self) -> &Link<T, V> {
+        return &self.left;
+    }
+
+    fn right(&self) -> &Link<T, V> {
+        return &self.right;
+    }
+
+    fn left_mut(&mut self) -> &mut Link<T, V> {
+        return &mut self.left;
+    }
+
+    fn right_mut(&mut self) -> &mut Link<T, V> {
+        return &mut self.right;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    /// Recomputes the cached height and largest end after either child has changed.
+    fn update(&mut self) {
+        self.height = 1 + max(height(&self.left), height(&self.right));
+        self.max_end = self.end;
+        for child in [&self.left, &self.right].iter() {
+            if let Some(ref child) = **child {
+                if child.max_end > self.max_end {
+                    self.max_end = child.max_end;
+                }
+            }
+        }
+    }
+}
+
+///
+/// Iterator over the spans of an `IntervalTree` that end after `after` and
+/// start before `before`, in order.
+///
+pub struct Overlapping<'a, T: 'a, V: 'a> {
+    stack: Vec<&'a IntervalNode<T, V>>,
+    after: Option<T>,
+    before: Bound<T>,
+}
+
+impl<'a, T: Ord + Copy, V> Overlapping<'a, T, V> {
+    fn ends_after(&self, end: T) -> bool {
+        return match self.after {
+            Some(after) => end > after,
+            None => true,
+        };
+    }
+
+    fn starts_before(&self, start: T) -> bool {
+        return match self.before {
+            Bound::Included(before) => start <= before,
+            Bound::Excluded(before) => start < before,
+            Bound::Unbounded => true,
+        };
+    }
+
+    fn push_left(&mut self, mut link: &'a Link<T, V>) {
+        while let Some(ref node) = *link {
+            if !self.ends_after(node.max_end) {
+                // every span in this subtree ends too early
+                return;
+            }
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, T: Ord + Copy, V> Iterator for Overlapping<'a, T, V> {
+    type Item = (Range<T>, &'a V);
+
+    fn next(&mut self) -> Option<(Range<T>, &'a V)> {
+        while let Some(node) = self.stack.pop() {
+            if !self.starts_before(node.start) {
+                // spans are ordered by start, so the rest start too late as well
+                self.stack.clear();
+                return None;
+            }
+            self.push_left(&node.right);
+            if self.ends_after(node.end) {
+                return Some((node.start..node.end, &node.value));
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    /// A short random span, so that spans overlap often.
+    fn random_span(random: &mut Random) -> Range<u32> {
+        let start = random.next(200) as u32;
+        return start..start + 1 + random.next(20) as u32;
+    }
+
+    /// Checks the AVL invariant, the cached heights and the cached largest
+    /// ends, returning the height of the subtree.
+    fn check_balanced<T: Ord + Copy, V>(link: &Link<T, V>) -> usize {
+        match *link {
+            None => 0,
+            Some(ref node) => {
+                let left = check_balanced(&node.left);
+                let right = check_balanced(&node.right);
+                assert!(left <= right + 1 && right <= left + 1, "subtree out of balance");
+                assert_eq!(1 + max(left, right), node.height, "cached height");
+                let mut max_end = node.end;
+                for child in [&node.left, &node.right].iter() {
+                    if let Some(ref child) = **child {
+                        max_end = max(max_end, child.max_end);
+                    }
+                }
+                assert!(max_end == node.max_end, "cached max end");
+                return node.height;
+            },
+        }
+    }
+
+    fn spans<'a, I: Iterator<Item = (Range<u32>, &'a u32)>>(iter: I) -> Vec<(Range<u32>, u32)> {
+        return iter.map(|(span, value)| (span, *value)).collect();
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree: IntervalTree<u32, ()> = IntervalTree::new();
+        assert_eq!(0, tree.size());
+        assert_eq!(0, tree.overlapping(0..10).count());
+        assert_eq!(0, tree.stabbing(0).count());
+        assert_eq!(0, tree.iter().count());
+    }
+
+    #[test]
+    fn duplicate_insert() {
+        let mut tree = IntervalTree::new();
+        assert!(!tree.insert(1..3, 1));
+        assert!(tree.insert(1..3, 2));
+        assert!(!tree.insert(1..4, 3));
+        assert_eq!(2, tree.size());
+        assert_eq!(vec![(1..3, 2), (1..4, 3)], spans(tree.iter()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_interval() {
+        let mut tree = IntervalTree::new();
+        tree.insert(3..3, ());
+    }
+
+    #[test]
+    fn touching_spans_do_not_overlap() {
+        let mut tree = IntervalTree::new();
+        tree.insert(0..5, 0);
+        tree.insert(5..10, 1);
+        assert_eq!(vec![(5..10, 1)], spans(tree.stabbing(5)));
+        assert_eq!(vec![(0..5, 0)], spans(tree.overlapping(0..5)));
+        assert_eq!(vec![(0..5, 0), (5..10, 1)], spans(tree.overlapping(4..6)));
+    }
+
+    #[test]
+    fn remove_missing() {
+        let mut tree = IntervalTree::new();
+        tree.insert(0..5, 0);
+        assert_eq!(None, tree.remove(&(0..4)));
+        assert_eq!(Some(0), tree.remove(&(0..5)));
+        assert_eq!(0, tree.size());
+    }
+
+    #[test]
+    fn sorted_inserts_stay_balanced() {
+        let mut tree = IntervalTree::new();
+        for i in 0..1000 {
+            tree.insert(i..i + 3, i);
+        }
+        let height = check_balanced(&tree.root);
+        assert!(height <= 15, "height {} is logarithmic", height);
+        assert_eq!(vec![(497..500, 497), (498..501, 498), (499..502, 499)],
+                   spans(tree.stabbing(499)));
+    }
+
+    #[test]
+    fn matches_naive_queries() {
+        let mut random = Random::new(38);
+        let mut tree = IntervalTree::new();
+        let mut naive: Vec<(Range<u32>, u32)> = Vec::new();
+
+        for i in 0..3000 {
+            let span = random_span(&mut random);
+            let existing = naive.iter().position(|&(ref s, _)| *s == span);
+            if random.next(3) == 0 {
+                let expected = existing.map(|index| naive.remove(index).1);
+                assert_eq!(expected, tree.remove(&span));
+            } else {
+                assert_eq!(existing.is_some(), tree.insert(span.clone(), i));
+                match existing {
+                    Some(index) => naive[index].1 = i,
+                    None => naive.push((span, i)),
+                }
+            }
+            assert_eq!(naive.len(), tree.size());
+            naive.sort_by_key(|&(ref span, _)| (span.start, span.end));
+
+            if i % 10 == 0 {
+                check_balanced(&tree.root);
+                let query = random_span(&mut random);
+                let expected: Vec<(Range<u32>, u32)> = naive.iter()
+                    .filter(|&&(ref span, _)| span.start < query.end && span.end > query.start)
+                    .cloned().collect();
+                assert_eq!(expected, spans(tree.overlapping(query.clone())), "{:?}", query);
+
+                let point = random.next(220) as u32;
+                let expected: Vec<(Range<u32>, u32)> = naive.iter()
+                    .filter(|&&(ref span, _)| span.start <= point && span.end > point)
+                    .cloned().collect();
+                assert_eq!(expected, spans(tree.stabbing(point)), "{}", point);
+                assert_eq!(naive, spans(tree.iter()));
+            }
+        }
+    }
+}
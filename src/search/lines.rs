@@ -0,0 +1,425 @@
+//!
+//! Maps offsets in a text to line numbers and columns. The start of every
+//! line is kept in a flat sorted vector, so building the index costs one
+//! allocation that grows geometrically rather than one per line, and lookups
+//! are a binary search.
+//!
+//! Lines end at `\n`, `\r\n` or a lone `\r`. Offsets are counted in bytes
+//! unless a `Unit` says otherwise; to convert between units the index also
+//! records where every character outside ASCII sits, which for mostly ASCII
+//! text costs next to nothing.
+//!
+//! ```
+//! use boyer_moore::search::lines::{LineIndex, Position, Unit};
+//!
+//! let index = LineIndex::new("first\r\nsecond\nthird");
+//! assert_eq!(3, index.line_count());
+//! assert_eq!(2, index.line(9));
+//! assert_eq!(Some(7..14), index.line_range(2));
+//! assert_eq!(Position { line: 2, column: 2 }, index.position(9, Unit::Byte));
+//! assert_eq!(Some(9), index.offset(Position { line: 2, column: 2 }, Unit::Utf16));
+//! ```
+
+use std::cmp::min;
+use std::ops::Range;
+use std::str;
+
+///
+/// What offsets and columns are counted in.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    /// UTF-8 bytes.
+    Byte,
+    /// Unicode scalar values, as yielded by `str::chars`.
+    Char,
+    /// UTF-16 code units, as used by the Language Server Protocol.
+    Utf16,
+}
+
+///
+/// A place in the text as a line, counting from 1, and a column, counting
+/// from 0 in some `Unit` from the start of the line.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineIndex {
+
+    ///
+    /// Indexes the lines of the text.
+    ///
+    pub fn new(text: &str) -> LineIndex {
+        return LineIndex::from_bytes(text.as_bytes());
+    }
+
+    ///
+    /// Indexes the lines of the text. Bytes that are not valid UTF-8 count
+    /// as one character each, as if replaced by U+FFFD.
+    ///
+    pub fn from_bytes(text: &[u8]) -> LineIndex {
+        let mut index = LineIndex { starts: vec![0], terminators: Vec::new(), wide: Vec::new(),
+                                    len: text.len() };
+        // how many more bytes than characters and UTF-16 units have been seen
+        let mut extra_chars = 0;
+        let mut extra_utf16 = 0;
+
+        let mut offset = 0;
+        while offset < text.len() {
+            let byte = text[offset];
+            if byte == b'\n' {
+                let crlf = offset > 0 && text[offset - 1] == b'\r';
+                index.starts.push(offset + 1);
+                index.terminators.push(if crlf { 2 } else { 1 });
+            } else if byte == b'\r' && text.get(offset + 1) != Some(&b'\n') {
+                index.starts.push(offset + 1);
+                index.terminators.push(1);
+            }
+            if byte < 0x80 {
+                offset += 1;
+                continue;
+            }
+
+            let (bytes, utf16) = char_width(&text[offset..]);
+            index.wide.push(WideChar { offsets: [offset, offset - extra_chars, offset - extra_utf16],
+                                       widths: [bytes, 1, utf16] });
+            extra_chars += bytes - 1;
+            extra_utf16 += bytes - utf16;
+            offset += bytes;
+        }
+        return index;
+    }
+
+    ///
+    /// The number of lines. A text ending in a line break has an empty last
+    /// line, and an empty text has a single empty line.
+    ///
+    pub fn line_count(&self) -> usize {
+        return self.starts.len();
+    }
+
+    ///
+    /// The line containing the byte offset, counting from 1. A line break
+    /// belongs to the line it terminates, and offsets past the end of the
+    /// text belong to the last line.
+    ///
+    pub fn line(&self, offset: usize) -> usize {
+        return match self.starts.binary_search(&offset) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+    }
+
+    ///
+    /// The byte offset of the first character of the line, counting lines
+    /// from 1.
+    ///
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return None;
+        }
+        return self.starts.get(line - 1).cloned();
+    }
+
+    ///
+    /// The bytes spanned by the line, counting lines from 1, including its
+    /// line break if it has one.
+    ///
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = match self.line_start(line) {
+            Some(start) => start,
+            None => return None,
+        };
+        let end = self.starts.get(line).cloned().unwrap_or(self.len);
+        return Some(start..end);
+    }
+
+    ///
+    /// Converts an offset from one unit to another. An offset inside a
+    /// character, such as between the two halves of a UTF-16 surrogate pair,
+    /// is moved back to the start of that character.
+    ///
+    /// ```
+    /// use boyer_moore::search::lines::{LineIndex, Unit};
+    ///
+    /// let index = LineIndex::new("naïve 🦀");
+    /// assert_eq!(6, index.convert(7, Unit::Byte, Unit::Char));
+    /// assert_eq!(11, index.convert(8, Unit::Utf16, Unit::Byte));
+    /// ```
+    ///
+    pub fn convert(&self, offset: usize, from: Unit, to: Unit) -> usize {
+        let (from, to) = (from as usize, to as usize);
+        // characters outside ASCII that start before the offset
+        let before = match self.wide.binary_search_by(|wide| wide.offsets[from].cmp(&offset)) {
+            Ok(index) => index,
+            Err(index) => index,
+        };
+        if before == 0 {
+            return offset;
+        }
+
+        // every character after the last wide one is a single unit of each kind
+        let wide = &self.wide[before - 1];
+        let past = offset - wide.offsets[from];
+        if past < wide.widths[from] {
+            return wide.offsets[to];
+        }
+        return wide.offsets[to] + wide.widths[to] + (past - wide.widths[from]);
+    }
+
+    ///
+    /// The line and column of the byte offset, with the column counted in
+    /// the given unit.
+    ///
+    pub fn position(&self, offset: usize, unit: Unit) -> Position {
+        let line = self.line(offset);
+        let start = self.convert(self.starts[line - 1], Unit::Byte, unit);
+        return Position { line: line, column: self.convert(offset, Unit::Byte, unit) - start };
+    }
+
+    ///
+    /// The byte offset of the position, whose column is counted in the given
+    /// unit. Columns past the end of the line are moved back to just before
+    /// its line break, as the Language Server Protocol asks.
+    ///
+    /// # Result
+    /// The offset, or `None` if the text does not have that many lines.
+    ///
+    pub fn offset(&self, position: Position, unit: Unit) -> Option<usize> {
+        let range = match self.line_range(position.line) {
+            Some(range) => range,
+            None => return None,
+        };
+        let end = match self.terminators.get(position.line - 1) {
+            Some(&terminator) => range.end - terminator as usize,
+            None => range.end,
+        };
+
+        let start = self.convert(range.start, Unit::Byte, unit);
+        let end = self.convert(end, Unit::Byte, unit);
+        return Some(self.convert(min(start + position.column, end), unit, Unit::Byte));
+    }
+}
+
+///
+/// The byte offsets of the starts of the lines of a text, in increasing
+/// order, along with what is needed to count columns in other units.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+    /// length in bytes of the line break ending each line but the last
+    terminators: Vec<u8>,
+    /// every character outside ASCII, in order
+    wide: Vec<WideChar>,
+    len: usize,
+}
+
+///
+/// A character that takes more than one byte, with its offset and width in
+/// each unit, indexed by `Unit as usize`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct WideChar {
+    offsets: [usize; 3],
+    widths: [usize; 3],
+}
+
+///
+/// The width in bytes and in UTF-16 code units of the character at the start
+/// of the bytes, treating an invalid byte as a character of its own.
+///
+fn char_width(bytes: &[u8]) -> (usize, usize) {
+    let prefix = &bytes[..min(4, bytes.len())];
+    let valid = match str::from_utf8(prefix) {
+        Ok(valid) => valid,
+        Err(error) => str::from_utf8(&prefix[..error.valid_up_to()]).unwrap(),
+    };
+    return match valid.chars().next() {
+        Some(c) => (c.len_utf8(), c.len_utf16()),
+        None => (1, 1),
+    };
+}
+
+///
+/// A `LineIndex` over a byte text that is only built the first time it is
+/// needed, so that searching files without matches never pays for it.
+///
+/// ```
+/// use boyer_moore::search::lines::LazyLineIndex;
+///
+/// let mut lines = LazyLineIndex::new(b"no\nmatches\nhere");
+/// assert!(!lines.is_built());
+/// assert_eq!(2, lines.get().line(4));
+/// assert!(lines.is_built());
+/// ```
+///
+pub struct LazyLineIndex<'a> {
+    text: &'a [u8],
+    index: Option<LineIndex>,
+}
+
+impl<'a> LazyLineIndex<'a> {
+    pub fn new(text: &'a [u8]) -> LazyLineIndex<'a> {
+        return LazyLineIndex { text: text, index: None };
+    }
+
+    ///
+    /// Returns the index, building it on the first call.
+    ///
+    pub fn get(&mut self) -> &LineIndex {
+        if self.index.is_none() {
+            self.index = Some(LineIndex::from_bytes(self.text));
+        }
+        return self.index.as_ref().unwrap();
+    }
+
+    pub fn is_built(&self) -> bool {
+        return self.index.is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    /// Line of the offset found by scanning back for line breaks.
+    fn naive_line(text: &str, offset: usize) -> usize {
+        let bytes = text.as_bytes();
+        let mut line = 1;
+        for i in 0..offset {
+            if bytes[i] == b'\n' || (bytes[i] == b'\r' && bytes.get(i + 1) != Some(&b'\n')) {
+                line += 1;
+            }
+        }
+        return line;
+    }
+
+    /// Offsets of every character boundary of the text in each unit.
+    fn boundaries(text: &str) -> Vec<[usize; 3]> {
+        let mut result = vec![[0, 0, 0]];
+        for c in text.chars() {
+            let last = result[result.len() - 1];
+            result.push([last[0] + c.len_utf8(), last[1] + 1, last[2] + c.len_utf16()]);
+        }
+        return result;
+    }
+
+    #[test]
+    fn empty_text() {
+        let index = LineIndex::new("");
+        assert_eq!(1, index.line_count());
+        assert_eq!(1, index.line(0));
+        assert_eq!(Some(0..0), index.line_range(1));
+        assert_eq!(None, index.line_range(2));
+        assert_eq!(None, index.line_start(0));
+        assert_eq!(Position { line: 1, column: 0 }, index.position(0, Unit::Utf16));
+        assert_eq!(Some(0), index.offset(Position { line: 1, column: 5 }, Unit::Char));
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let index = LineIndex::new("a\nb\n");
+        assert_eq!(3, index.line_count());
+        assert_eq!(1, index.line(1));
+        assert_eq!(2, index.line(2));
+        assert_eq!(3, index.line(4));
+        assert_eq!(Some(2..4), index.line_range(2));
+        assert_eq!(Some(4..4), index.line_range(3));
+    }
+
+    #[test]
+    fn offsets_past_the_end() {
+        let index = LineIndex::new("a\nb");
+        assert_eq!(2, index.line(100));
+    }
+
+    #[test]
+    fn line_endings() {
+        let index = LineIndex::new("a\r\nb\rc\n\rd");
+        assert_eq!(5, index.line_count());
+        assert_eq!(vec![Some(0..3), Some(3..5), Some(5..7), Some(7..8), Some(8..9)],
+                   (1..6).map(|line| index.line_range(line)).collect::<Vec<_>>());
+        assert_eq!(1, index.line(1));
+        assert_eq!(1, index.line(2));
+        assert_eq!(Some(1), index.offset(Position { line: 1, column: 9 }, Unit::Byte));
+        assert_eq!(Some(4), index.offset(Position { line: 2, column: 9 }, Unit::Byte));
+        assert_eq!(Some(7), index.offset(Position { line: 4, column: 9 }, Unit::Byte));
+        assert_eq!(None, index.offset(Position { line: 6, column: 0 }, Unit::Byte));
+    }
+
+    #[test]
+    fn columns_in_each_unit() {
+        let text = "x\né🦀b";
+        let index = LineIndex::new(text);
+        let b = text.find('b').unwrap();
+        assert_eq!(Position { line: 2, column: 6 }, index.position(b, Unit::Byte));
+        assert_eq!(Position { line: 2, column: 2 }, index.position(b, Unit::Char));
+        assert_eq!(Position { line: 2, column: 3 }, index.position(b, Unit::Utf16));
+        assert_eq!(Some(b), index.offset(Position { line: 2, column: 3 }, Unit::Utf16));
+    }
+
+    #[test]
+    fn inside_a_character() {
+        let index = LineIndex::new("🦀");
+        assert_eq!(0, index.convert(1, Unit::Utf16, Unit::Byte));
+        assert_eq!(0, index.convert(2, Unit::Byte, Unit::Char));
+        assert_eq!(1, index.convert(4, Unit::Byte, Unit::Char));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let index = LineIndex::from_bytes(b"a\xff\xe2\x82\nb");
+        assert_eq!(2, index.line_count());
+        assert_eq!(4, index.convert(4, Unit::Byte, Unit::Char));
+        assert_eq!(Some(5), index.offset(Position { line: 2, column: 0 }, Unit::Utf16));
+    }
+
+    #[test]
+    fn matches_naive_conversions() {
+        let mut random = Random::new(40);
+        let units = [Unit::Byte, Unit::Char, Unit::Utf16];
+        for _ in 0..300 {
+            let text = random.string(&["a", "\n", "\r", "é", "€", "🦀"], 30);
+            let index = LineIndex::new(&text);
+            let boundaries = boundaries(&text);
+
+            for offsets in boundaries.iter() {
+                for from in 0..3 {
+                    for to in 0..3 {
+                        assert_eq!(offsets[to], index.convert(offsets[from], units[from], units[to]),
+                                   "{:?} from {} to {} in {:?}", offsets, from, to, text);
+                    }
+                }
+
+                let line = naive_line(&text, offsets[0]);
+                assert_eq!(line, index.line(offsets[0]));
+                let start = boundaries.iter().find(|b| naive_line(&text, b[0]) == line).unwrap();
+                // the `\n` of a `\r\n` is past the end of the line's columns
+                let bytes = text.as_bytes();
+                let crlf = offsets[0] > 0 && bytes[offsets[0] - 1] == b'\r'
+                    && bytes.get(offsets[0]) == Some(&b'\n');
+                let expected = if crlf { offsets[0] - 1 } else { offsets[0] };
+                for unit in 0..3 {
+                    let position = Position { line: line, column: offsets[unit] - start[unit] };
+                    assert_eq!(position, index.position(offsets[0], units[unit]));
+                    assert_eq!(Some(expected), index.offset(position, units[unit]),
+                               "{:?} in {:?}", position, text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_index_builds_once() {
+        let mut lazy = LazyLineIndex::new(b"a\nb\nc");
+        assert!(!lazy.is_built());
+        assert_eq!(3, lazy.get().line_count());
+        assert!(lazy.is_built());
+        assert_eq!(&LineIndex::new("a\nb\nc"), lazy.get());
+    }
+}
@@ -0,0 +1,245 @@
+//!
+//! Approximate search that allows a number of mismatched bytes, that is, a
+//! Hamming distance, between the pattern and the text it matches.
+//!
+//! The pattern is cut into `k + 1` pieces. An occurrence with at most `k`
+//! mismatches has at least one piece that matches exactly, so each piece is
+//! searched for with Boyer-Moore and only the alignments it suggests are
+//! compared in full. The fewer mismatches are allowed, the longer the pieces
+//! and the further the searches skip.
+//!
+//! ```
+//! use boyer_moore::search::mismatch::{HammingMatch, MismatchSearcher};
+//!
+//! let searcher = MismatchSearcher::new("invoice", 1);
+//! let matches: Vec<HammingMatch> = searcher.find_iter("lnvoice 1nv0ice invoice").collect();
+//! assert_eq!(vec![HammingMatch { start: 0, mismatches: 1 },
+//!                 HammingMatch { start: 16, mismatches: 0 }], matches);
+//! ```
+
+use search::searcher::Searcher;
+
+///
+/// Where an approximate match starts, and how many of its bytes differ from
+/// the pattern. It spans as many bytes as the pattern.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HammingMatch {
+    pub start: usize,
+    pub mismatches: usize,
+}
+
+///
+/// A searcher for the places where the text differs from the pattern in at
+/// most `k` bytes.
+///
+#[derive(Clone)]
+pub struct MismatchSearcher {
+    pattern: Vec<u8>,
+    max_mismatches: usize,
+    /// the pieces of the pattern with their offsets in it, or none when the
+    /// pattern is too short to cut, so that every alignment matches
+    pieces: Vec<(usize, Searcher)>,
+}
+
+impl MismatchSearcher {
+
+    ///
+    /// Builds the searcher for the pattern, allowing up to `max_mismatches`
+    /// mismatched bytes.
+    ///
+    pub fn new(pattern: &str, max_mismatches: usize) -> MismatchSearcher {
+        return MismatchSearcher::from_bytes(pattern.as_bytes(), max_mismatches);
+    }
+
+    ///
+    /// Builds the searcher for a pattern that is not necessarily UTF-8.
+    ///
+    pub fn from_bytes(pattern: &[u8], max_mismatches: usize) -> MismatchSearcher {
+        let m = pattern.len();
+        let mut pieces = Vec::new();
+        if max_mismatches < m {
+            let count = max_mismatches + 1;
+            for i in 0..count {
+                let (start, end) = (i * m / count, (i + 1) * m / count);
+                pieces.push((start, Searcher::from_bytes(&pattern[start..end])));
+            }
+        }
+        return MismatchSearcher { pattern: pattern.to_vec(), max_mismatches: max_mismatches,
+                                  pieces: pieces };
+    }
+
+    /// The number of mismatched bytes a match may have.
+    pub fn max_mismatches(&self) -> usize {
+        return self.max_mismatches;
+    }
+
+    ///
+    /// Returns every alignment of the pattern with at most `k` mismatches,
+    /// including ones that overlap each other, in increasing order.
+    ///
+    pub fn find_iter<'s, 'h, H: AsRef<[u8]> + ?Sized>(&'s self, haystack: &'h H)
+                                                     -> MismatchIter<'s, 'h> {
+        let haystack = haystack.as_ref();
+        let next = (0..self.pieces.len()).map(|i| self.candidate(i, haystack, 0)).collect();
+        return MismatchIter { searcher: self, haystack: haystack, next: next, position: 0 };
+    }
+
+    ///
+    /// The first alignment at or after `start` where the piece matches
+    /// exactly.
+    ///
+    fn candidate(&self, piece: usize, haystack: &[u8], start: usize) -> Option<usize> {
+        let (offset, ref searcher) = self.pieces[piece];
+        return searcher.find_at(haystack, start + offset)
+            .map(|found| found - offset)
+            .filter(|alignment| alignment + self.pattern.len() <= haystack.len());
+    }
+
+    ///
+    /// The number of mismatches at the alignment, if it is at most `k`.
+    ///
+    fn verify(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        let mut mismatches = 0;
+        for (a, b) in self.pattern.iter().zip(&haystack[start..]) {
+            if a != b {
+                mismatches += 1;
+                if mismatches > self.max_mismatches {
+                    return None;
+                }
+            }
+        }
+        return Some(mismatches);
+    }
+
+    /// The number of mismatches at the alignment, however many there are.
+    fn count_mismatches(&self, haystack: &[u8], start: usize) -> usize {
+        return self.pattern.iter().zip(&haystack[start..]).filter(|&(a, b)| a != b).count();
+    }
+}
+
+impl Searcher {
+
+    ///
+    /// A searcher for the same pattern that also accepts occurrences with up
+    /// to `max_mismatches` mismatched bytes.
+    ///
+    pub fn with_mismatches(&self, max_mismatches: usize) -> MismatchSearcher {
+        return MismatchSearcher::from_bytes(self.pattern(), max_mismatches);
+    }
+}
+
+///
+/// Iterator over the approximate matches of a pattern, in increasing order.
+///
+pub struct MismatchIter<'s, 'h> {
+    searcher: &'s MismatchSearcher,
+    haystack: &'h [u8],
+    /// the next alignment suggested by each piece
+    next: Vec<Option<usize>>,
+    /// the next alignment to try when there are no pieces
+    position: usize,
+}
+
+impl<'s, 'h> Iterator for MismatchIter<'s, 'h> {
+    type Item = HammingMatch;
+
+    fn next(&mut self) -> Option<HammingMatch> {
+        let m = self.searcher.pattern.len();
+        if self.searcher.pieces.is_empty() {
+            // too few bytes to tell apart, so every alignment is close enough
+            if m == 0 || self.position + m > self.haystack.len() {
+                return None;
+            }
+            self.position += 1;
+            let start = self.position - 1;
+            let mismatches = self.searcher.count_mismatches(self.haystack, start);
+            return Some(HammingMatch { start: start, mismatches: mismatches });
+        }
+
+        loop {
+            let start = match self.next.iter().filter_map(|next| *next).min() {
+                Some(start) => start,
+                None => return None,
+            };
+            for piece in 0..self.next.len() {
+                if self.next[piece] == Some(start) {
+                    self.next[piece] = self.searcher.candidate(piece, self.haystack, start + 1);
+                }
+            }
+            if let Some(mismatches) = self.searcher.verify(self.haystack, start) {
+                return Some(HammingMatch { start: start, mismatches: mismatches });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    /// Every alignment with its mismatch count, checked one by one.
+    fn naive(pattern: &[u8], haystack: &[u8], k: usize) -> Vec<HammingMatch> {
+        let mut result = Vec::new();
+        if pattern.is_empty() || pattern.len() > haystack.len() {
+            return result;
+        }
+        for start in 0..haystack.len() - pattern.len() + 1 {
+            let mismatches = pattern.iter().zip(&haystack[start..]).filter(|&(a, b)| a != b).count();
+            if mismatches <= k {
+                result.push(HammingMatch { start: start, mismatches: mismatches });
+            }
+        }
+        return result;
+    }
+
+    #[test]
+    fn exact_when_no_mismatches() {
+        let searcher = MismatchSearcher::new("abc", 0);
+        let starts: Vec<usize> = searcher.find_iter("abcabd abc").map(|m| m.start).collect();
+        assert_eq!(vec![0, 7], starts);
+    }
+
+    #[test]
+    fn overlapping_matches() {
+        let searcher = MismatchSearcher::new("aaa", 1);
+        let matches: Vec<HammingMatch> = searcher.find_iter("aaba").collect();
+        assert_eq!(vec![HammingMatch { start: 0, mismatches: 1 },
+                        HammingMatch { start: 1, mismatches: 1 }], matches);
+    }
+
+    #[test]
+    fn more_mismatches_than_pattern() {
+        let searcher = MismatchSearcher::new("ab", 5);
+        let matches: Vec<HammingMatch> = searcher.find_iter("xbz").collect();
+        assert_eq!(vec![HammingMatch { start: 0, mismatches: 1 },
+                        HammingMatch { start: 1, mismatches: 2 }], matches);
+    }
+
+    #[test]
+    fn empty_pattern() {
+        assert_eq!(0, MismatchSearcher::new("", 0).find_iter("abc").count());
+        assert_eq!(0, MismatchSearcher::new("", 2).find_iter("abc").count());
+    }
+
+    #[test]
+    fn from_searcher() {
+        let searcher = Searcher::new("needle").with_mismatches(2);
+        assert_eq!(2, searcher.max_mismatches());
+        assert_eq!(Some(4), searcher.find_iter("hay noodle").map(|m| m.start).next());
+    }
+
+    #[test]
+    fn matches_naive_search() {
+        let mut random = Random::new(45);
+        for _ in 0..3000 {
+            let haystack = random.bytes(b"abc", 30);
+            let pattern = random.bytes(b"abc", 8);
+            let k = random.next(4);
+            let searcher = MismatchSearcher::from_bytes(&pattern, k);
+            assert_eq!(naive(&pattern, &haystack, k), searcher.find_iter(&haystack).collect::<Vec<_>>(),
+                       "{:?} in {:?} with {}", pattern, haystack, k);
+        }
+    }
+}
@@ -12,4 +12,19 @@
 
 
 pub mod search;
+pub mod boundary;
+pub mod edit;
+pub mod fold;
+pub mod grep;
+pub mod incremental;
+pub mod interval;
+pub mod lines;
+pub mod mismatch;
+pub mod normalize;
+pub mod pattern;
+pub mod searcher;
+pub mod split;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod tree;
+pub mod wildcard;
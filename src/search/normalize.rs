@@ -0,0 +1,413 @@
+//!
+//! Matching that does not depend on how text is normalized, so that a
+//! precomposed "é" finds an "e" followed by a combining acute accent and the
+//! other way round.
+//!
+//! The pattern and the text are both brought to the same normalization form
+//! before searching. The text is normalized a piece at a time, a piece being
+//! a character that is not a combining mark together with the marks after
+//! it, so every offset in the normalized text can be traced back to the
+//! piece of the original it came from. Pieces that normalization leaves
+//! alone are kept in runs whose offsets map one to one.
+//!
+//! ```
+//! use boyer_moore::search::normalize::{Form, NormalizingSearcher};
+//!
+//! let searcher = NormalizingSearcher::new("café", Form::Nfc);
+//! let text = "a cafe\u{301} au lait";
+//! assert_eq!(vec![2..8], searcher.find_all(text));
+//! ```
+
+extern crate unicode_normalization;
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use self::unicode_normalization::char::canonical_combining_class;
+use self::unicode_normalization::UnicodeNormalization;
+
+use search::search::MatchKind;
+use search::searcher::Searcher;
+
+///
+/// A Unicode normalization form.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Form {
+    /// canonical composition: only sequences that are the same character
+    /// are made equal, such as "é" and "e" with a combining acute
+    Nfc,
+    /// compatibility composition: also makes equal characters that are
+    /// formatting variants of each other, such as "ﬁ" and "fi"
+    Nfkc,
+}
+
+impl Form {
+
+    /// Returns the text in this normalization form.
+    pub fn normalize(self, text: &str) -> String {
+        return match self {
+            Form::Nfc => text.nfc().collect(),
+            Form::Nfkc => text.nfkc().collect(),
+        };
+    }
+}
+
+///
+/// A run of the normalized text that came from one place in the original.
+///
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    /// where the run starts in the normalized text
+    normalized: usize,
+    /// where the run starts in the original text
+    original: usize,
+    /// whether the run is the same in both, so offsets inside it map one to
+    /// one; otherwise it is a single piece that maps as a whole
+    exact: bool,
+}
+
+///
+/// A text in a normalization form, with the way back to the offsets of the
+/// text it was made from.
+///
+#[derive(Clone, Debug)]
+pub struct Normalized {
+    text: String,
+    spans: Vec<Span>,
+    original_len: usize,
+}
+
+impl Normalized {
+
+    ///
+    /// Normalizes the text, remembering where each part of the result came
+    /// from.
+    ///
+    pub fn new(original: &str, form: Form) -> Normalized {
+        return Normalized::with_transform(original, |text| form.normalize(text));
+    }
+
+    ///
+    /// Transforms the text a piece at a time, remembering where each part
+    /// of the result came from. The transform must act on pieces on their
+    /// own, such as a normalization form or a folding does: applied to two
+    /// pieces together it gives the two results one after the other, unless
+    /// the pieces combine with each other.
+    ///
+    pub fn with_transform<F: Fn(&str) -> String>(original: &str, transform: F) -> Normalized {
+        let mut text = String::with_capacity(original.len());
+        let mut spans: Vec<Span> = Vec::new();
+        // the original bytes of the last piece, or of the pieces it had to
+        // be merged with, and where its normalized form starts
+        let mut group = 0..0;
+        let mut group_start = 0;
+
+        for piece in pieces(original) {
+            let bytes = &original[piece.clone()];
+            if !bytes.is_ascii() && !group.is_empty() {
+                // a piece can combine with the one before it, such as the
+                // halves of a Hangul syllable, in which case they are
+                // normalized together
+                let joined = transform(&original[group.start..piece.end]);
+                if joined != format!("{}{}", &text[group_start..], transform(bytes)) {
+                    text.truncate(group_start);
+                    text.push_str(&joined);
+                    if spans.last().is_some_and(|span| span.normalized == group_start) {
+                        spans.pop();
+                    }
+                    spans.push(Span { normalized: group_start, original: group.start, exact: false });
+                    group.end = piece.end;
+                    continue;
+                }
+            }
+
+            let normalized = transform(bytes);
+            let exact = normalized == bytes;
+            if !exact || !spans.last().is_some_and(|span| span.exact) {
+                spans.push(Span { normalized: text.len(), original: piece.start, exact: exact });
+            }
+            group = piece;
+            group_start = text.len();
+            text.push_str(&normalized);
+        }
+        return Normalized { text: text, spans: spans, original_len: original.len() };
+    }
+
+    /// The normalized text.
+    pub fn as_str(&self) -> &str {
+        return &self.text;
+    }
+
+    ///
+    /// The bytes of the original text that a range of the normalized text
+    /// came from. A range that begins or ends inside a piece that
+    /// normalization changed is widened to take in all of it.
+    ///
+    /// ```
+    /// use boyer_moore::search::normalize::{Form, Normalized};
+    ///
+    /// let normalized = Normalized::new("ﬁne", Form::Nfkc);
+    /// assert_eq!("fine", normalized.as_str());
+    /// assert_eq!(0..3, normalized.original_range(0..1));
+    /// assert_eq!(3..5, normalized.original_range(2..4));
+    /// ```
+    ///
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        return self.original_start(range.start)..self.original_end(range.end);
+    }
+
+    /// The original offset of the start of whatever begins at the offset.
+    fn original_start(&self, offset: usize) -> usize {
+        return match self.spans_up_to(offset + 1) {
+            0 => 0,
+            count => {
+                let span = self.spans[count - 1];
+                if span.exact { span.original + (offset - span.normalized) } else { span.original }
+            },
+        };
+    }
+
+    /// The original offset of the end of whatever finishes at the offset.
+    fn original_end(&self, offset: usize) -> usize {
+        return match self.spans_up_to(offset) {
+            0 => 0,
+            count => {
+                let span = self.spans[count - 1];
+                if span.exact {
+                    span.original + (offset - span.normalized)
+                } else {
+                    self.spans.get(count).map_or(self.original_len, |next| next.original)
+                }
+            },
+        };
+    }
+
+    ///
+    /// Returns the bytes of the original text covered by every match of the
+    /// searcher in the normalized text. A match that starts or ends inside a
+    /// piece, such as between a letter and an accent that did not compose
+    /// with it, does not count, so that "q" no more matches "q\u{301}" than
+    /// "e" matches "e\u{301}".
+    ///
+    /// ```
+    /// use boyer_moore::search::normalize::{Form, Normalized};
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let normalized = Normalized::new("q\u{301} q", Form::Nfc);
+    /// assert_eq!(vec![4..5], normalized.find_all(&Searcher::new("q")));
+    /// ```
+    ///
+    pub fn find_all(&self, searcher: &Searcher) -> Vec<Range<usize>> {
+        let len = searcher.pattern().len();
+        let mut found = Vec::new();
+        let mut position = 0;
+        while let Some(offset) = searcher.find_at(self.text.as_bytes(), position) {
+            if !starts_piece(&self.text, offset) || !starts_piece(&self.text, offset + len) {
+                position = offset + 1;
+                continue;
+            }
+            found.push(self.original_range(offset..offset + len));
+            position = match searcher.kind() {
+                MatchKind::Overlapping => offset + 1,
+                MatchKind::NonOverlapping => offset + len,
+            };
+        }
+        return found;
+    }
+
+    /// The number of spans that start before the normalized offset.
+    fn spans_up_to(&self, offset: usize) -> usize {
+        let search = self.spans.binary_search_by(|span| {
+            if span.normalized < offset { Ordering::Less } else { Ordering::Greater }
+        });
+        return search.unwrap_or_else(|count| count);
+    }
+}
+
+///
+/// Whether a piece starts at the offset, or it is the end of the text. An
+/// offset that is not on a character boundary starts nothing.
+///
+pub(crate) fn starts_piece(text: &str, offset: usize) -> bool {
+    if !text.is_char_boundary(offset) {
+        return false;
+    }
+    return text[offset..].chars().next().is_none_or(|c| canonical_combining_class(c) == 0);
+}
+
+///
+/// The number of non-overlapping occurrences of the pattern that start and
+/// end on pieces, checked one offset at a time. Tests compare searches with it.
+///
+#[cfg(test)]
+pub(crate) fn naive_count(pattern: &str, text: &str) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while !pattern.is_empty() && i + pattern.len() <= text.len() {
+        let end = i + pattern.len();
+        if &text.as_bytes()[i..end] == pattern.as_bytes()
+            && starts_piece(text, i) && starts_piece(text, end) {
+            count += 1;
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    return count;
+}
+
+///
+/// Splits the text before every character that is not a combining mark, so
+/// each piece is such a character and the marks that follow it.
+///
+fn pieces(text: &str) -> Vec<Range<usize>> {
+    let mut starts: Vec<usize> = text.char_indices()
+        .filter(|&(i, c)| i == 0 || canonical_combining_class(c) == 0)
+        .map(|(i, _)| i)
+        .collect();
+    starts.push(text.len());
+    return starts.windows(2).map(|pair| pair[0]..pair[1]).collect();
+}
+
+///
+/// A searcher that finds the pattern whatever the normalization of the
+/// pattern and of the text.
+///
+#[derive(Clone)]
+pub struct NormalizingSearcher {
+    searcher: Searcher,
+    form: Form,
+}
+
+impl NormalizingSearcher {
+
+    ///
+    /// Builds the searcher for the pattern, comparing in the given form.
+    ///
+    pub fn new(pattern: &str, form: Form) -> NormalizingSearcher {
+        return NormalizingSearcher { searcher: Searcher::new(&form.normalize(pattern)), form: form };
+    }
+
+    /// Sets whether a match must be a whole word, in the normalized text.
+    pub fn whole_word(mut self, whole_word: bool) -> NormalizingSearcher {
+        self.searcher = self.searcher.whole_word(whole_word);
+        return self;
+    }
+
+    /// Sets whether a match must be a whole line.
+    pub fn whole_line(mut self, whole_line: bool) -> NormalizingSearcher {
+        self.searcher = self.searcher.whole_line(whole_line);
+        return self;
+    }
+
+    ///
+    /// Returns the bytes of the original text covered by every
+    /// non-overlapping match.
+    ///
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        return Normalized::new(text, self.form).find_all(&self.searcher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    #[test]
+    fn decomposed_text() {
+        let searcher = NormalizingSearcher::new("é", Form::Nfc);
+        assert_eq!(vec![1..4, 5..7], searcher.find_all("xe\u{301} é"));
+    }
+
+    #[test]
+    fn decomposed_pattern() {
+        let searcher = NormalizingSearcher::new("e\u{301}t\u{e9}", Form::Nfc);
+        assert_eq!(vec![0..5], searcher.find_all("été"));
+    }
+
+    #[test]
+    fn marks_in_other_order() {
+        // a dot below and an acute in either order are the same character
+        let searcher = NormalizingSearcher::new("e\u{323}\u{301}", Form::Nfc);
+        assert_eq!(vec![0..5], searcher.find_all("e\u{301}\u{323}"));
+    }
+
+    #[test]
+    fn base_letter_alone_does_not_match() {
+        let searcher = NormalizingSearcher::new("e", Form::Nfc);
+        assert_eq!(Vec::<Range<usize>>::new(), searcher.find_all("e\u{301}"));
+        // with no precomposed form, the letter and the mark stay apart
+        let searcher = NormalizingSearcher::new("q", Form::Nfc);
+        assert_eq!(Vec::<Range<usize>>::new(), searcher.find_all("q\u{301}"));
+        let searcher = NormalizingSearcher::new("\u{301}", Form::Nfc);
+        assert_eq!(Vec::<Range<usize>>::new(), searcher.find_all("q\u{301}"));
+    }
+
+    #[test]
+    fn rejected_match_does_not_hide_the_next() {
+        // the first candidate ends before a mark, the second overlaps it
+        let searcher = NormalizingSearcher::new("x\u{347}x", Form::Nfc);
+        assert_eq!(vec![3..7], searcher.find_all("x\u{347}x\u{347}x"));
+    }
+
+    #[test]
+    fn compatibility_forms() {
+        let nfc = NormalizingSearcher::new("fi", Form::Nfc);
+        assert_eq!(Vec::<Range<usize>>::new(), nfc.find_all("ﬁne"));
+        let nfkc = NormalizingSearcher::new("fi", Form::Nfkc);
+        assert_eq!(vec![0..3], nfkc.find_all("ﬁne"));
+        let nfkc = NormalizingSearcher::new("x2", Form::Nfkc);
+        assert_eq!(vec![1..4], nfkc.find_all("ax²"));
+    }
+
+    #[test]
+    fn hangul_jamo_compose_across_pieces() {
+        // the leading and vowel jamo are separate pieces that make one syllable
+        let normalized = Normalized::new("a\u{1100}\u{1161}b", Form::Nfc);
+        assert_eq!("a\u{ac00}b", normalized.as_str());
+        assert_eq!(1..7, normalized.original_range(1..4));
+        assert_eq!(7..8, normalized.original_range(4..5));
+    }
+
+    #[test]
+    fn exact_runs_map_one_to_one() {
+        let normalized = Normalized::new("abc e\u{301} def", Form::Nfc);
+        assert_eq!("abc é def", normalized.as_str());
+        assert_eq!(1..3, normalized.original_range(1..3));
+        assert_eq!(4..7, normalized.original_range(4..6));
+        assert_eq!(8..11, normalized.original_range(7..10));
+        assert_eq!(12..12, normalized.original_range(11..11));
+    }
+
+    #[test]
+    fn whole_word_in_normalized_text() {
+        let searcher = NormalizingSearcher::new("cafe", Form::Nfc).whole_word(true);
+        assert_eq!(Vec::<Range<usize>>::new(), searcher.find_all("cafe\u{301}"));
+        assert_eq!(vec![0..4], searcher.find_all("cafe!"));
+    }
+
+    #[test]
+    fn matches_normalize_back() {
+        let alphabet = ["a", "e", "\u{e9}", "\u{301}", "\u{323}", "\u{1100}", "\u{1161}", "ﬁ", "i"];
+        let mut random = Random::new(49);
+        for _ in 0..2000 {
+            let text = random.string(&alphabet, 12);
+            let pattern = random.string(&alphabet, 3);
+            for &form in [Form::Nfc, Form::Nfkc].iter() {
+                let searcher = NormalizingSearcher::new(&pattern, form);
+                let normalized = Normalized::new(&text, form);
+                assert_eq!(form.normalize(&text), normalized.as_str(), "{:?}", text);
+                let found = searcher.find_all(&text);
+                assert_eq!(naive_count(&form.normalize(&pattern), normalized.as_str()), found.len(),
+                           "{:?} in {:?}", pattern, text);
+                for range in found {
+                    let found = form.normalize(&text[range.clone()]);
+                    assert!(found.contains(&form.normalize(&pattern)),
+                            "{:?} in {:?} at {:?}", pattern, text, range);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//!
+//! Patterns whose positions match sets of bytes, written with bracketed
+//! character classes such as `[0-9a-f]` or `[^A-Z]`, and searched with the
+//! bad character rule of Boyer-Moore generalized to sets.
+//!
+//! A pattern is made of literal characters, characters escaped with `\`
+//! (which makes `\[`, `\]`, `\^`, `\-` and `\\` literal) and classes. A class
+//! lists ASCII characters and ranges between them, and matches any one byte
+//! in the list, or any byte outside it when it starts with `^`. A `]` right
+//! after the opening `[` or `[^` is a member rather than the end of the class.
+//!
+//! Like `Searcher`, this works on bytes: a literal character outside ASCII
+//! becomes one position per byte of its UTF-8 encoding, and a negated class
+//! matches single bytes of such characters.
+//!
+//! ```
+//! use boyer_moore::search::pattern::ClassSearcher;
+//!
+//! let searcher = ClassSearcher::new("0x[0-9a-f][0-9a-f]").unwrap();
+//! assert_eq!(vec![6], searcher.find_iter("0xZZ, 0x1f").collect::<Vec<usize>>());
+//! ```
+
+use std::fmt;
+use std::iter::Peekable;
+
+use search::search;
+use search::search::ALPHABET_SIZE;
+use search::search::MatchKind;
+
+/// The character that makes the next one in a pattern literal.
+pub const ESCAPE: char = '\\';
+
+/// The reasons a pattern can be rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatternError {
+    /// the pattern ends with an escape that has nothing to escape
+    TrailingEscape,
+    /// the wildcard is the escape character or is not a single byte
+    InvalidWildcard(char),
+    /// a `[` without the `]` that ends the class
+    UnclosedClass,
+    /// a class member that is not a single byte
+    NonAsciiClassMember(char),
+    /// a class range whose start comes after its end
+    InvalidRange(char, char),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatternError::TrailingEscape =>
+                write!(f, "pattern ends with an unfinished escape '{}'", ESCAPE),
+            PatternError::InvalidWildcard(c) =>
+                write!(f, "'{}' cannot be used as the wildcard", c),
+            PatternError::UnclosedClass =>
+                write!(f, "character class is missing its closing ']'"),
+            PatternError::NonAsciiClassMember(c) =>
+                write!(f, "character class member '{}' is not ASCII", c),
+            PatternError::InvalidRange(start, end) =>
+                write!(f, "character class range '{}-{}' is out of order", start, end),
+        }
+    }
+}
+
+///
+/// A set of bytes, such as the bytes one position of a pattern matches.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ByteSet {
+    bits: [u64; 4],
+}
+
+impl ByteSet {
+
+    /// The set holding no bytes.
+    pub fn new() -> ByteSet {
+        return ByteSet { bits: [0; 4] };
+    }
+
+    /// The set holding just the byte.
+    pub fn single(byte: u8) -> ByteSet {
+        let mut set = ByteSet::new();
+        set.insert(byte);
+        return set;
+    }
+
+    pub fn insert(&mut self, byte: u8) {
+        self.bits[(byte / 64) as usize] |= 1 << (byte % 64);
+    }
+
+    /// Adds every byte from `start` to `end`, both included.
+    pub fn insert_range(&mut self, start: u8, end: u8) {
+        for byte in start as usize..end as usize + 1 {
+            self.insert(byte as u8);
+        }
+    }
+
+    pub fn contains(&self, byte: u8) -> bool {
+        return self.bits[(byte / 64) as usize] & (1 << (byte % 64)) != 0;
+    }
+
+    /// The set of every byte not in this one.
+    pub fn complement(&self) -> ByteSet {
+        let mut set = *self;
+        for bits in set.bits.iter_mut() {
+            *bits = !*bits;
+        }
+        return set;
+    }
+
+    /// The bytes in the set, in increasing order.
+    pub fn iter(&self) -> ByteSetIter {
+        return ByteSetIter { set: *self, next: 0 };
+    }
+}
+
+///
+/// Iterator over the bytes in a `ByteSet`.
+///
+pub struct ByteSetIter {
+    set: ByteSet,
+    next: usize,
+}
+
+impl Iterator for ByteSetIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.next < ALPHABET_SIZE {
+            let byte = self.next;
+            self.next += 1;
+            if self.set.contains(byte as u8) {
+                return Some(byte);
+            }
+        }
+        return None;
+    }
+}
+
+///
+/// Compiles a pattern into the set of bytes each of its positions matches.
+///
+pub fn parse(pattern: &str) -> Result<Vec<ByteSet>, PatternError> {
+    let mut sets = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let literal = match c {
+            ESCAPE => chars.next().ok_or(PatternError::TrailingEscape)?,
+            '[' => {
+                sets.push(parse_class(&mut chars)?);
+                continue;
+            },
+            _ => c,
+        };
+        let mut buffer = [0; 4];
+        sets.extend(literal.encode_utf8(&mut buffer).bytes().map(ByteSet::single));
+    }
+    return Ok(sets);
+}
+
+///
+/// Parses the members of a class up to and including its closing `]`.
+///
+fn parse_class<I: Iterator<Item = char>>(chars: &mut Peekable<I>)
+                                         -> Result<ByteSet, PatternError> {
+    let negated = chars.peek() == Some(&'^');
+    if negated {
+        chars.next();
+    }
+
+    let mut set = ByteSet::new();
+    let mut first = true;
+    loop {
+        let start = match chars.next() {
+            None => return Err(PatternError::UnclosedClass),
+            Some(']') if !first => break,
+            Some(ESCAPE) => chars.next().ok_or(PatternError::TrailingEscape)?,
+            Some(c) => c,
+        };
+        first = false;
+        let start_byte = class_member(start)?;
+
+        if chars.peek() != Some(&'-') {
+            set.insert(start_byte);
+            continue;
+        }
+        chars.next();
+        let end = match chars.next() {
+            None => return Err(PatternError::UnclosedClass),
+            Some(']') => {
+                // a `-` at the end of a class is a member of its own
+                set.insert(start_byte);
+                set.insert(b'-');
+                break;
+            },
+            Some(ESCAPE) => chars.next().ok_or(PatternError::TrailingEscape)?,
+            Some(c) => c,
+        };
+        let end_byte = class_member(end)?;
+        if start_byte > end_byte {
+            return Err(PatternError::InvalidRange(start, end));
+        }
+        set.insert_range(start_byte, end_byte);
+    }
+    return Ok(if negated { set.complement() } else { set });
+}
+
+fn class_member(c: char) -> Result<u8, PatternError> {
+    if !c.is_ascii() {
+        return Err(PatternError::NonAsciiClassMember(c));
+    }
+    return Ok(c as u8);
+}
+
+///
+/// A searcher for a pattern in which every position matches a set of bytes.
+/// After a mismatch the pattern moves on to the nearest position to the left
+/// whose set holds the mismatched byte, so it still skips ahead quickly as
+/// long as the classes are small.
+///
+#[derive(Clone)]
+pub struct ClassSearcher {
+    sets: Vec<ByteSet>,
+    bad_char: [Vec<i32>; ALPHABET_SIZE],
+    kind: MatchKind,
+}
+
+impl ClassSearcher {
+
+    ///
+    /// Compiles the pattern and builds the searcher for it.
+    ///
+    pub fn new(pattern: &str) -> Result<ClassSearcher, PatternError> {
+        return Ok(ClassSearcher::from_sets(parse(pattern)?));
+    }
+
+    ///
+    /// Builds the searcher from the set of bytes each position matches.
+    ///
+    pub fn from_sets(sets: Vec<ByteSet>) -> ClassSearcher {
+        let bad_char = search::bad_character_table(sets.iter().map(|set| set.iter()), sets.len());
+        return ClassSearcher { sets: sets, bad_char: bad_char, kind: MatchKind::NonOverlapping };
+    }
+
+    ///
+    /// Sets whether `find_iter` reports matches that overlap each other. The
+    /// default is `MatchKind::NonOverlapping`.
+    ///
+    pub fn match_kind(mut self, kind: MatchKind) -> ClassSearcher {
+        self.kind = kind;
+        return self;
+    }
+
+    /// The number of bytes every match spans.
+    pub fn len(&self) -> usize {
+        return self.sets.len();
+    }
+
+    /// Whether the pattern is empty, in which case it matches nowhere.
+    pub fn is_empty(&self) -> bool {
+        return self.sets.is_empty();
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern.
+    ///
+    pub fn find<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H) -> Option<usize> {
+        return self.find_at(haystack.as_ref(), 0);
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern that starts
+    /// at or after `start`.
+    /// # Arguments
+    /// * `haystack` - the body to search in
+    /// * `start` - the offset to begin searching from
+    ///
+    pub fn find_at(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        let m = self.sets.len();
+        if m == 0 || start > haystack.len() || haystack.len() - start < m {
+            return None;
+        }
+
+        // alignment of the end of the pattern relative to the search
+        let mut k = start + m - 1;
+        while k < haystack.len() {
+            let mut p_index = m - 1;
+            let mut c_index = k;
+            while self.sets[p_index].contains(haystack[c_index]) {
+                if p_index == 0 {
+                    return Some(c_index);
+                }
+                p_index -= 1;
+                c_index -= 1;
+            }
+            let bad_char = self.bad_char[haystack[c_index] as usize][p_index];
+            k += (p_index as i32 - bad_char) as usize;
+        }
+        return None;
+    }
+
+    ///
+    /// Returns the offsets of every occurrence of the pattern, overlapping
+    /// each other or not depending on the match kind.
+    ///
+    pub fn find_iter<'s, 'h, H: AsRef<[u8]> + ?Sized>(&'s self, haystack: &'h H)
+                                                     -> ClassFindIter<'s, 'h> {
+        return ClassFindIter { searcher: self, haystack: haystack.as_ref(), position: 0 };
+    }
+}
+
+///
+/// Iterator over the offsets of the occurrences of a class pattern.
+///
+pub struct ClassFindIter<'s, 'h> {
+    searcher: &'s ClassSearcher,
+    haystack: &'h [u8],
+    position: usize,
+}
+
+impl<'s, 'h> Iterator for ClassFindIter<'s, 'h> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let found = self.searcher.find_at(self.haystack, self.position);
+        if let Some(offset) = found {
+            self.position = match self.searcher.kind {
+                MatchKind::Overlapping => offset + 1,
+                MatchKind::NonOverlapping => offset + self.searcher.len(),
+            };
+        }
+        return found;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    fn members(pattern: &str) -> Vec<Vec<u8>> {
+        return parse(pattern).unwrap().iter()
+            .map(|set| set.iter().map(|b| b as u8).collect())
+            .collect();
+    }
+
+    #[test]
+    fn literals_and_escapes() {
+        assert_eq!(vec![b"a".to_vec(), b"[".to_vec(), b"\\".to_vec()], members("a\\[\\\\"));
+        assert_eq!(vec![vec![0xc3], vec![0xa9]], members("é"));
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(vec![b"0123abc".to_vec()], members("[0-3a-c]"));
+        assert_eq!(vec![b"-ab".to_vec()], members("[ab-]"));
+        assert_eq!(vec![b"]a".to_vec()], members("[]a]"));
+        assert_eq!(vec![b"-]".to_vec()], members("[\\]\\-]"));
+        assert_eq!(254, parse("[^ab]").unwrap()[0].iter().count());
+        assert!(!parse("[^]]").unwrap()[0].contains(b']'));
+    }
+
+    #[test]
+    fn full_range() {
+        let set = parse("[\u{0}-\u{7f}]").unwrap()[0];
+        assert_eq!(128, set.iter().count());
+        let mut all = ByteSet::new();
+        all.insert_range(0, 255);
+        assert_eq!(ByteSet::new().complement(), all);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Err(PatternError::UnclosedClass), parse("a[bc"));
+        assert_eq!(Err(PatternError::UnclosedClass), parse("[a-"));
+        assert_eq!(Err(PatternError::TrailingEscape), parse("[a\\"));
+        assert_eq!(Err(PatternError::NonAsciiClassMember('é')), parse("[é]"));
+        assert_eq!(Err(PatternError::InvalidRange('z', 'a')), parse("[z-a]"));
+        assert_eq!("character class range 'z-a' is out of order",
+                   PatternError::InvalidRange('z', 'a').to_string());
+    }
+
+    #[test]
+    fn hex_digits() {
+        let searcher = ClassSearcher::new("#[0-9a-fA-F][0-9a-fA-F][0-9a-fA-F]").unwrap();
+        assert_eq!(vec![5, 14], searcher.find_iter("#xyz #fA0 #12 #9e1").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn negated_class() {
+        let searcher = ClassSearcher::new("a[^0-9]").unwrap();
+        assert_eq!(vec![2, 7], searcher.find_iter("a1ab a2a-").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let searcher = ClassSearcher::new("").unwrap();
+        assert!(searcher.is_empty());
+        assert_eq!(None, searcher.find("abc"));
+    }
+
+    #[test]
+    fn matches_naive_search() {
+        let mut random = Random::new(44);
+        let pieces = ["a", "b", "c", "[ab]", "[^a]", "[a-c]", "[bc]"];
+        for _ in 0..2000 {
+            let haystack: Vec<u8> = (0..random.next(30)).map(|_| b"abcd"[random.next(4)]).collect();
+            let pattern: String = (0..random.next(5)).map(|_| pieces[random.next(pieces.len())])
+                .collect();
+            let sets = parse(&pattern).unwrap();
+            let searcher = ClassSearcher::from_sets(sets.clone()).match_kind(MatchKind::Overlapping);
+
+            let mut expected = Vec::new();
+            for i in 0..haystack.len() {
+                if !sets.is_empty() && i + sets.len() <= haystack.len()
+                    && sets.iter().enumerate().all(|(j, set)| set.contains(haystack[i + j])) {
+                    expected.push(i);
+                }
+            }
+            assert_eq!(expected, searcher.find_iter(&haystack).collect::<Vec<_>>(),
+                       "{:?} in {:?}", pattern, haystack);
+        }
+    }
+}
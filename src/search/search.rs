@@ -7,11 +7,31 @@ use std::io::Chars;
 use std::io::Read;
 use std::iter::Peekable;
 use std::mem;
+use std::ops::Range;
 use self::ansi_term::Colour;
 
-use search::tree;
+pub(crate) const ALPHABET_SIZE: usize = 256;
 
-const ALPHABET_SIZE: usize = 256;
+///
+/// Whether matches are allowed to share characters with each other.
+///
+/// ```
+/// use boyer_moore::search::search::{self, MatchKind};
+///
+/// let overlapping = search::search_string_with("aa", "aaaa".to_string(), MatchKind::Overlapping);
+/// let non_overlapping = search::search_string_with("aa", "aaaa".to_string(),
+///                                                  MatchKind::NonOverlapping);
+/// assert_eq!(vec![0, 1, 2], overlapping);
+/// assert_eq!(vec![0, 2], non_overlapping);
+/// ```
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    /// every occurrence is reported, even ones that begin inside another
+    Overlapping,
+    /// scanning resumes after the end of each occurrence, like `str::matches`
+    NonOverlapping,
+}
 
 macro_rules! init_array(
     ($ty:ty, $len:expr, $val:expr) => (
@@ -37,7 +57,7 @@ macro_rules! max(
     )
 );
 
-fn match_length(pattern: &Vec<char>, mut i1: usize, mut i2: usize) -> usize {
+fn match_length<T: PartialEq>(pattern: &Vec<T>, mut i1: usize, mut i2: usize) -> usize {
     if i1 == i2 {
         return pattern.len() - i1;
     } else {
@@ -51,16 +71,19 @@ fn match_length(pattern: &Vec<char>, mut i1: usize, mut i2: usize) -> usize {
     }
 }
 
-fn preprocess(pattern: &str) -> Vec<usize> {
+///
+/// Computes the Z array of the pattern: the length of the longest substring
+/// starting at each position that is also a prefix of the pattern.
+///
+pub(crate) fn z_values<T: PartialEq>(pattern: &Vec<T>) -> Vec<usize> {
     if pattern.len() == 0 {
         return vec![];
     } else if pattern.len() == 1 {
         return vec![1];
     } else {
-        let chars: Vec<char> = pattern.chars().collect();
         let mut result = vec![0; pattern.len()];
         result[0] = pattern.len();
-        result[1] = match_length(&chars, 0, 1);
+        result[1] = match_length(pattern, 0, 1);
 
         for i in 2..(1 + result[1]) {
             result[i] = result[1] - i + 1;
@@ -77,12 +100,13 @@ fn preprocess(pattern: &str) -> Vec<usize> {
                 if b < a {
                     result[i] = b;
                 } else {
-                    result[i] = match_length(&chars, a, right + 1);
+                    // the first `a` characters are already known to match
+                    result[i] = a + match_length(pattern, a, right + 1);
                     left = i;
                     right = i + result[i] - 1;
                 }
             } else {
-                result[i] = match_length(&chars, 0, i);
+                result[i] = match_length(pattern, 0, i);
                 if result[i] > 0 {
                     left = i;
                     right = i + result[i] - 1;
@@ -94,14 +118,22 @@ fn preprocess(pattern: &str) -> Vec<usize> {
 }
 
 fn get_good_suffix(pattern: &str) -> Vec<i32> {
-    let p: String = pattern.to_string().chars().rev().collect();
-    let p_str: &str = p.as_str();
-    
+    let chars: Vec<char> = pattern.chars().collect();
+    return good_suffix_table(&chars);
+}
+
+///
+/// For every position, the end of the rightmost copy of the suffix starting
+/// there that appears elsewhere in the pattern, or -1 if there is none.
+///
+pub(crate) fn good_suffix_table<T: PartialEq + Clone>(pattern: &Vec<T>) -> Vec<i32> {
+    let p: Vec<T> = pattern.iter().cloned().rev().collect();
+
     let mut result: Vec<i32> = vec![-1; pattern.len()];
-    let mut preprocess = preprocess(p_str);
+    let mut preprocess = z_values(&p);
     preprocess.reverse();
 
-    for i in 0..(pattern.len() - 1) {
+    for i in 0..(pattern.len().saturating_sub(1)) {
         let j = pattern.len() - preprocess[i];
         if j != preprocess.len() {
             result[j] = i as i32;
@@ -112,8 +144,17 @@ fn get_good_suffix(pattern: &str) -> Vec<i32> {
 }
 
 fn get_full_shift(pattern: &str) -> Vec<usize> {
+    let chars: Vec<char> = pattern.chars().collect();
+    return full_shift_table(&chars);
+}
+
+///
+/// For every position, the length of the longest suffix of the pattern
+/// starting at or after it that is also a prefix of the pattern.
+///
+pub(crate) fn full_shift_table<T: PartialEq>(pattern: &Vec<T>) -> Vec<usize> {
     let mut result = vec![0 ; pattern.len()];
-    let mut z = preprocess(pattern);
+    let mut z = z_values(pattern);
     z.reverse();
     let mut longest: usize = 0;
 
@@ -128,17 +169,46 @@ fn get_full_shift(pattern: &str) -> Vec<usize> {
     return result;    
 }
 
+///
+/// The smallest shift that lines the pattern up with itself again, used to
+/// move past a match without missing an overlapping one.
+/// # Arguments
+/// * `full_shift` - the full shift table of the pattern
+///
+pub(crate) fn period(full_shift: &Vec<usize>) -> usize {
+    if full_shift.len() > 1 {
+        return full_shift.len() - full_shift[1];
+    } else {
+        return full_shift.len();
+    }
+}
+
 fn get_bad_character(pattern: &str) -> [Vec<i32>; ALPHABET_SIZE] {
+    return bad_character_table(pattern.chars().map(|c| Some(c as usize)), pattern.len());
+}
+
+///
+/// For every symbol in the alphabet, the rightmost position before each
+/// pattern index that the symbol can match at, or -1 if there is none. A
+/// position may match a whole set of symbols, such as a character class.
+/// # Arguments
+/// * `positions` - the pattern, as the symbols each position matches
+/// * `len` - the length of the pattern
+///
+pub(crate) fn bad_character_table<I, S>(positions: I, len: usize) -> [Vec<i32>; ALPHABET_SIZE]
+    where I: Iterator<Item = S>, S: IntoIterator<Item = usize> {
     let mut result: [Vec<i32>; ALPHABET_SIZE] =
-        init_array!(Vec<i32>, ALPHABET_SIZE, Vec::with_capacity(pattern.len()));
+        init_array!(Vec<i32>, ALPHABET_SIZE, Vec::with_capacity(len));
 
     for v in result.iter_mut() {
         v.push(-1);
     }
 
     let mut alpha: [i32; ALPHABET_SIZE] = [-1 ; ALPHABET_SIZE];
-    for (i, c) in pattern.chars().enumerate() {
-        alpha[c as usize] = i as i32;
+    for (i, symbols) in positions.enumerate() {
+        for c in symbols {
+            alpha[c] = i as i32;
+        }
         for (j, a) in alpha.iter().enumerate() {
             result[j].push(*a);
         }
@@ -155,23 +225,6 @@ fn load_file<'a>(input: File) -> String {
     return contents;
 }
 
-/// mapping from character offset to line count
-fn build_line_tree(chars: &Vec<char>) -> tree::Tree<usize, usize> {
-    let mut tree = tree::Tree::new();
-    let mut line_count = 1;
-
-    tree.insert(0, line_count);
-    line_count += 1;
-    
-    for (offset, character) in chars.iter().enumerate() {
-        if *character == '\n' {
-            tree.insert(offset, line_count);
-            line_count += 1;
-        }
-    }    
-    return tree;
-}
-
 ///
 /// Searches the file for the given pattern and returns the list
 /// of places that it occurs in.
@@ -184,31 +237,21 @@ pub fn search_file(pattern: &str, input: File) {
 }
 
 ///
-/// Pretty prints the match found.
+/// Pretty prints the match found. The text that matched is highlighted as it
+/// appears in the contents, which need not be the same as the pattern.
 /// # Arguments
-/// * `result` - the beggining offset that the pattern matched at
-/// * `pattern` - the actual pattern that matched
+/// * `contents` - the search contents
+/// * `span` - the bytes of the contents that matched
 /// * `line` - which line the result was found on
-/// * `chars` - the search contents
 ///
-fn print_result(result: usize, pattern: &str, line: usize, chars: &Vec<char>) {
-    let mut min_offset = result;
-    let mut max_offset = result;
+pub(crate) fn print_result(contents: &str, span: Range<usize>, line: usize) {
+    let min_offset = contents[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let max_offset = contents[span.end..].find('\n').map_or(contents.len(), |i| span.end + i);
 
-    while min_offset != 0 && chars[min_offset] != '\n' {
-        min_offset -= 1;
-    }
-    
-    while max_offset != chars.len() - 1 && chars[max_offset] != '\n' {
-        max_offset += 1;
-    }
-    
-    let prefix: String = chars[(min_offset + 1)..result].into_iter()
-        .cloned().collect();
-    let suffix: String = chars[(result + pattern.len())..max_offset].into_iter()
-        .cloned().collect();
+    let prefix = &contents[min_offset..span.start];
+    let suffix = contents[span.end..max_offset].trim_end_matches('\r');
 
-    println!("{}: {}{}{}", line, prefix, Colour::Green.paint(pattern), suffix);
+    println!("{}: {}{}{}", line, prefix, Colour::Green.paint(&contents[span]), suffix);
 }
 
 ///
@@ -230,8 +273,23 @@ fn print_result(result: usize, pattern: &str, line: usize, chars: &Vec<char>) {
 ///
 ///
 pub fn search_string(pattern: &str, contents: String) -> Vec<usize> {
+    return search_string_with(pattern, contents, MatchKind::Overlapping);
+}
+
+///
+/// Finds the occurences of the `pattern` in the `contents` search string,
+/// with the given overlap semantics.
+/// # Arguments
+/// * `pattern` the string to search for
+/// * `contents` the body to search within
+/// * `kind` whether matches may overlap each other
+///
+/// # Result
+/// The list of offsets that pattern was found at
+///
+pub fn search_string_with(pattern: &str, contents: String, kind: MatchKind) -> Vec<usize> {
     let chars = contents.chars().collect();
-    return search(pattern, &chars);
+    return search(pattern, &chars, kind);
 }
 
 fn search_buffer(pattern_input: &str, buffer: &mut BufRead) -> Vec<usize> {
@@ -242,9 +300,9 @@ fn search_buffer(pattern_input: &str, buffer: &mut BufRead) -> Vec<usize> {
     let bad_char_table = get_bad_character(pattern_input);
     let good_suffix = get_good_suffix(pattern_input);
     let full_shift = get_full_shift(pattern_input);
+    let period = period(&full_shift);
 
     let mut k: usize = pattern.len() - 1;    
-    let mut prev_k: i32 = -1;
     let mut buffer: Vec<char> = vec![];
 
     loop {
@@ -271,9 +329,9 @@ fn search_buffer(pattern_input: &str, buffer: &mut BufRead) -> Vec<usize> {
         if valid {
             let i = k + 1 - pattern.len();
             results.push(i);
-            k += 1;
+            k += period;
             println!("found match at {:?}", i);
-            buffer.drain(0..1);
+            buffer.drain(0..period);
         } else {
             let bad_char = bad_char_table[buffer[p_index] as usize][p_index];
             let char_shift = p_index as i32 - bad_char;
@@ -289,9 +347,6 @@ fn search_buffer(pattern_input: &str, buffer: &mut BufRead) -> Vec<usize> {
                 suffix_shift = pattern.len() as i32 - good_suffix[p_index + 1] - 1;
             }
             let shift = max!(char_shift, suffix_shift) as usize;
-            if shift >= p_index + 1 {
-                prev_k = k as i32;
-            }
             k += shift;
             buffer.drain(0..shift);
         }
@@ -303,14 +358,19 @@ fn search_buffer(pattern_input: &str, buffer: &mut BufRead) -> Vec<usize> {
 /// Finds the occurences of the pattern in the search area. Returns the
 /// starting index of every occurence.
 ///
+/// In overlapping mode the pattern moves by its period after a match. The
+/// characters it still overlaps with are already known to match, so the
+/// comparison stops early once it reaches them (Galil's rule).
+///
 /// # Arguments
 /// * `pattern` - the string to try and search for
 /// * `contents` - the body to search in
+/// * `kind` - whether matches may overlap each other
 ///
 /// # Result
 /// The list of offsets that the pattern was found at
 ///
-fn search(pattern: &str, contents: &Vec<char>) -> Vec<usize> {
+fn search(pattern: &str, contents: &Vec<char>, kind: MatchKind) -> Vec<usize> {
     let mut results = Vec::new();
     let p_vec: Vec<char> = pattern.chars().collect();
 
@@ -321,9 +381,11 @@ fn search(pattern: &str, contents: &Vec<char>) -> Vec<usize> {
     let bad_char_table = get_bad_character(pattern);
     let good_suffix = get_good_suffix(pattern);
     let full_shift = get_full_shift(pattern);
+    let period = period(&full_shift);
 
     // alignment of the end of the pattern relative to the search
     let mut k: usize = pattern.len() - 1; 
+    // end of the previous match, when the current alignment still overlaps it
     let mut prev_k: i32 = -1;
 
     while k < contents.len() {
@@ -345,7 +407,16 @@ fn search(pattern: &str, contents: &Vec<char>) -> Vec<usize> {
         if valid { // match found
             let i = k + 1 - pattern.len();
             results.push(i);
-            k += 1;
+            match kind {
+                MatchKind::Overlapping => {
+                    prev_k = k as i32;
+                    k += period;
+                },
+                MatchKind::NonOverlapping => {
+                    prev_k = -1;
+                    k += pattern.len();
+                },
+            }
         } else { // no match, calculate shift distance
             let bad_char = bad_char_table[contents[c_index] as usize][p_index];
             let char_shift = p_index as i32 - bad_char;
@@ -362,9 +433,7 @@ fn search(pattern: &str, contents: &Vec<char>) -> Vec<usize> {
 
             }
             let shift = max!(char_shift, suffix_shift);
-            if shift >= p_index as i32 + 1 {
-                prev_k = k as i32;
-            }
+            prev_k = -1;
             k += shift as usize;           
         }
     }
@@ -374,6 +443,7 @@ fn search(pattern: &str, contents: &Vec<char>) -> Vec<usize> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use search::test_util::Random;
     use std::io::Cursor;
 
     #[test]
@@ -480,6 +550,56 @@ mod tests {
         assert_eq!(10, results.len(), "all matches");
     }
 
+    #[test]
+    fn overlapping_matches() {
+        let results = search_string_with("aa", "aaaa".to_string(), MatchKind::Overlapping);
+        assert_eq!(vec![0, 1, 2], results, "every start reported");
+    }
+
+    #[test]
+    fn non_overlapping_matches() {
+        let results = search_string_with("aa", "aaaa".to_string(), MatchKind::NonOverlapping);
+        assert_eq!(vec![0, 2], results, "resumes after each match");
+    }
+
+    #[test]
+    fn overlapping_shifts_by_period() {
+        let input = "abcabcabcab".to_string();
+        let overlapping = search_string_with("abcab", input.clone(), MatchKind::Overlapping);
+        assert_eq!(vec![0, 3, 6], overlapping);
+        let non_overlapping = search_string_with("abcab", input, MatchKind::NonOverlapping);
+        assert_eq!(vec![0, 6], non_overlapping);
+    }
+
+    #[test]
+    fn buffer_overlapping_matches() {
+        let mut buffer = Cursor::new("abababa".to_string());
+        let results = search_buffer("aba", &mut buffer);
+        assert_eq!(vec![0, 2, 4], results);
+    }
+
+    #[test]
+    fn match_kinds_agree_with_naive_search() {
+        let mut random = Random::new(3);
+        for _ in 0..2000 {
+            let input = random.string(&["a", "b", "c"], 29);
+            let pattern: String = (0..1 + random.next(5)).map(|_| ["a", "b", "c"][random.next(3)])
+                .collect();
+
+            let overlapping: Vec<usize> = (0..input.len())
+                .filter(|&i| input[i..].starts_with(pattern.as_str())).collect();
+            let non_overlapping: Vec<usize> = input.match_indices(pattern.as_str())
+                .map(|(i, _)| i).collect();
+
+            assert_eq!(overlapping,
+                       search_string_with(&pattern, input.clone(), MatchKind::Overlapping),
+                       "overlapping {} in {}", pattern, input);
+            assert_eq!(non_overlapping,
+                       search_string_with(&pattern, input.clone(), MatchKind::NonOverlapping),
+                       "non-overlapping {} in {}", pattern, input);
+        }
+    }
+
     #[test]
     fn match_length_equal() {
         let chars = "this is a test of this".chars().collect();
@@ -503,25 +623,37 @@ mod tests {
 
     #[test]
     fn small_preprocess() {
-        let result = super::preprocess("a");
+        let result = super::z_values(&vec!['a']);
         assert_eq!(1, result.len(), "correct size");
         assert_eq!(1, result[0], "correct substring size");
     }
 
     #[test]
     fn empty_preprocess() {
-        assert_eq!(0, super::preprocess("").len(), "empty result");
+        assert_eq!(0, super::z_values::<char>(&vec![]).len(), "empty result");
     }
 
     #[test]
     fn simple_preprocess() {
         let input = "hanhan";
-        let result = super::preprocess(input);        
+        let result = super::z_values(&input.chars().collect());
         assert_eq!(input.len(), result[0]);
         assert_eq!(0, result[1]);
         assert_eq!(3, result[3]);
     }
 
+    #[test]
+    fn preprocess_extends_known_match() {
+        let result = super::z_values(&"bcbcb".chars().collect());
+        assert_eq!(vec![5, 0, 3, 0, 1], result);
+    }
+
+    #[test]
+    fn periodic_pattern_after_partial_match() {
+        let result = search_string("bcbcb", String::from("bbcbbcbcbacaac"));
+        assert_eq!(vec![4], result);
+    }
+
     #[test]
     fn full_shift_simple() {
         let input = "hanhan";
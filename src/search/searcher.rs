@@ -0,0 +1,821 @@
+use std::cmp;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use search::boundary;
+use search::search;
+use search::search::ALPHABET_SIZE;
+use search::search::MatchKind;
+
+/// Number of bytes read from the input at a time when streaming.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of bytes on either side of a match that deciding whether it is a
+/// whole word or at a line start or end may look at.
+const CONTEXT: usize = 4;
+
+///
+/// A Boyer-Moore searcher with its shift tables computed once up front, so
+/// the same pattern can be run over many inputs.
+///
+/// The searcher works on bytes. Searching a `&str` searches its UTF-8
+/// encoding, so every reported offset is a byte offset that lies on a
+/// character boundary. An empty pattern never matches.
+///
+/// ```
+/// use boyer_moore::search::searcher::Searcher;
+///
+/// let searcher = Searcher::new("fox");
+/// assert_eq!(Some(4), searcher.find("the fox"));
+/// assert_eq!("the cat", searcher.replace_all("the fox", "cat"));
+/// ```
+///
+#[derive(Clone)]
+pub struct Searcher {
+    pattern: Vec<u8>,
+    bad_char: [Vec<i32>; ALPHABET_SIZE],
+    good_suffix: Vec<i32>,
+    full_shift: Vec<usize>,
+    period: usize,
+    kind: MatchKind,
+    whole_word: bool,
+    line_start: bool,
+    line_end: bool,
+}
+
+impl Searcher {
+
+    ///
+    /// Builds the searcher for the given pattern.
+    ///
+    pub fn new(pattern: &str) -> Searcher {
+        return Searcher::from_bytes(pattern.as_bytes());
+    }
+
+    ///
+    /// Builds the searcher for a pattern that is not necessarily UTF-8.
+    ///
+    pub fn from_bytes(pattern: &[u8]) -> Searcher {
+        let pattern: Vec<u8> = pattern.to_vec();
+        let full_shift = search::full_shift_table(&pattern);
+        return Searcher {
+            bad_char: search::bad_character_table(pattern.iter().map(|b| Some(*b as usize)),
+                                                  pattern.len()),
+            good_suffix: search::good_suffix_table(&pattern),
+            period: search::period(&full_shift),
+            full_shift: full_shift,
+            pattern: pattern,
+            kind: MatchKind::NonOverlapping,
+            whole_word: false,
+            line_start: false,
+            line_end: false,
+        };
+    }
+
+    ///
+    /// Sets whether `find_iter` reports matches that overlap each other. The
+    /// default is `MatchKind::NonOverlapping`. Replacing and splitting always
+    /// use non-overlapping matches.
+    ///
+    /// ```
+    /// use boyer_moore::search::search::MatchKind;
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let searcher = Searcher::new("aa").match_kind(MatchKind::Overlapping);
+    /// assert_eq!(vec![0, 1, 2], searcher.find_iter("aaaa").collect::<Vec<usize>>());
+    /// ```
+    ///
+    pub fn match_kind(mut self, kind: MatchKind) -> Searcher {
+        self.kind = kind;
+        return self;
+    }
+
+    /// Whether `find_iter` reports matches that overlap each other.
+    pub fn kind(&self) -> MatchKind {
+        return self.kind;
+    }
+
+    ///
+    /// Sets whether a match must be a whole word, that is, not have a word
+    /// character just before or just after it. See `boundary` for what a
+    /// word character is.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let searcher = Searcher::new("test").whole_word(true);
+    /// assert_eq!(Some(7), searcher.find("latest test"));
+    /// ```
+    ///
+    pub fn whole_word(mut self, whole_word: bool) -> Searcher {
+        self.whole_word = whole_word;
+        return self;
+    }
+
+    /// Sets whether a match must begin at the start of a line.
+    pub fn line_start(mut self, line_start: bool) -> Searcher {
+        self.line_start = line_start;
+        return self;
+    }
+
+    /// Sets whether a match must finish at the end of a line.
+    pub fn line_end(mut self, line_end: bool) -> Searcher {
+        self.line_end = line_end;
+        return self;
+    }
+
+    ///
+    /// Sets whether a match must be a whole line, both starting and ending
+    /// one.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let searcher = Searcher::new("done").whole_line(true);
+    /// assert_eq!(Some(13), searcher.find("not done yet\ndone\n"));
+    /// ```
+    ///
+    pub fn whole_line(self, whole_line: bool) -> Searcher {
+        return self.line_start(whole_line).line_end(whole_line);
+    }
+
+    ///
+    /// Whether an occurrence of the pattern at the offset satisfies the
+    /// word and line conditions.
+    ///
+    fn accepts(&self, haystack: &[u8], offset: usize) -> bool {
+        let end = offset + self.pattern.len();
+        if self.whole_word
+            && (boundary::word_before(haystack, offset) || boundary::word_after(haystack, end)) {
+            return false;
+        }
+        if self.line_start && !boundary::is_line_start(haystack, offset) {
+            return false;
+        }
+        if self.line_end && !boundary::is_line_end(haystack, end) {
+            return false;
+        }
+        return true;
+    }
+
+    /// Whether any of the word and line conditions are set.
+    fn has_conditions(&self) -> bool {
+        return self.whole_word || self.line_start || self.line_end;
+    }
+
+    ///
+    /// How many bytes on each side of a match the word and line conditions
+    /// look at: enough for a character or a line break.
+    ///
+    pub(crate) fn context_len(&self) -> usize {
+        return if self.has_conditions() { boundary::MAX_CHAR_LEN } else { 0 };
+    }
+
+    /// The pattern this searcher looks for.
+    pub fn pattern(&self) -> &[u8] {
+        return &self.pattern;
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern.
+    ///
+    pub fn find<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H) -> Option<usize> {
+        return self.find_at(haystack.as_ref(), 0);
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern that starts
+    /// at or after `start`.
+    /// # Arguments
+    /// * `haystack` - the body to search in
+    /// * `start` - the offset to begin searching from
+    ///
+    pub fn find_at(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        return self.scan(haystack.len(), start, |i| haystack[i],
+                         |offset| self.accepts(haystack, offset));
+    }
+
+    ///
+    /// Runs the search over a haystack of `len` bytes whose contents are
+    /// given by `byte_at`, so the same loop can walk a buffer in either
+    /// direction. Occurrences that `accept` turns down are skipped.
+    ///
+    fn scan<F, A>(&self, len: usize, start: usize, byte_at: F, accept: A) -> Option<usize>
+        where F: Fn(usize) -> u8, A: Fn(usize) -> bool {
+        let m = self.pattern.len();
+        if m == 0 || start > len || len - start < m {
+            return None;
+        }
+
+        // alignment of the end of the pattern relative to the search
+        let mut k = start + m - 1;
+        while k < len {
+            let mut p_index = m - 1;
+            let mut c_index = k;
+            let mut matched = false;
+            while self.pattern[p_index] == byte_at(c_index) {
+                if p_index == 0 {
+                    matched = true;
+                    break;
+                }
+                p_index -= 1;
+                c_index -= 1;
+            }
+            if !matched {
+                k += self.shift(p_index, byte_at(c_index));
+            } else if accept(c_index) {
+                return Some(c_index);
+            } else {
+                // the next occurrence is at least a period further on
+                k += self.period;
+            }
+        }
+        return None;
+    }
+
+    ///
+    /// Returns the offsets of every occurrence of the pattern, overlapping
+    /// each other or not depending on the match kind.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let offsets: Vec<usize> = Searcher::new("aa").find_iter("aaaaa").collect();
+    /// assert_eq!(vec![0, 2], offsets);
+    /// ```
+    ///
+    pub fn find_iter<'a, H: AsRef<[u8]> + ?Sized>(&'a self, haystack: &'a H) -> FindIter<'a> {
+        return FindIter { searcher: self, haystack: haystack.as_ref(), position: 0 };
+    }
+
+    ///
+    /// Distance to move the pattern after a mismatch, taking the larger of
+    /// the bad character and good suffix rules.
+    /// # Arguments
+    /// * `p_index` - the index in the pattern that failed to match
+    /// * `byte` - the byte in the search area that it was compared against
+    ///
+    fn shift(&self, p_index: usize, byte: u8) -> usize {
+        let m = self.pattern.len();
+        let bad_char = self.bad_char[byte as usize][p_index];
+        let char_shift = p_index as i32 - bad_char;
+
+        let suffix_shift;
+        if p_index + 1 == m {
+            suffix_shift = 1;
+        } else if self.good_suffix[p_index + 1] == -1 {
+            // matched suffix does not appear anywhere in the input pattern
+            suffix_shift = (m - self.full_shift[p_index + 1]) as i32;
+        } else {
+            // matched suffix does appear in the input pattern
+            suffix_shift = m as i32 - self.good_suffix[p_index + 1] - 1;
+        }
+        return cmp::max(char_shift, suffix_shift) as usize;
+    }
+
+    ///
+    /// Replaces every non-overlapping occurrence of the pattern.
+    ///
+    pub fn replace_all(&self, haystack: &str, replacement: &str) -> String {
+        return self.replacen(haystack, replacement, usize::MAX);
+    }
+
+    ///
+    /// Replaces the first `count` non-overlapping occurrences of the pattern.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let searcher = Searcher::new("ab");
+    /// assert_eq!("X X ab", searcher.replacen("ab ab ab", "X", 2));
+    /// ```
+    ///
+    pub fn replacen(&self, haystack: &str, replacement: &str, count: usize) -> String {
+        let bytes = self.replacen_bytes(haystack.as_bytes(), replacement.as_bytes(), count);
+        // matches of a UTF-8 pattern can only begin and end on character
+        // boundaries, so splicing in UTF-8 keeps the result valid
+        return String::from_utf8(bytes).expect("replacement produced invalid UTF-8");
+    }
+
+    ///
+    /// Replaces every non-overlapping occurrence of the pattern in raw bytes.
+    ///
+    pub fn replace_all_bytes(&self, haystack: &[u8], replacement: &[u8]) -> Vec<u8> {
+        return self.replacen_bytes(haystack, replacement, usize::MAX);
+    }
+
+    ///
+    /// Replaces the first `count` non-overlapping occurrences of the pattern
+    /// in raw bytes.
+    ///
+    pub fn replacen_bytes(&self, haystack: &[u8], replacement: &[u8], count: usize) -> Vec<u8> {
+        let mut result = Vec::with_capacity(haystack.len());
+        let mut position = 0;
+        let mut replaced = 0;
+        while replaced < count {
+            match self.find_at(haystack, position) {
+                Some(offset) => {
+                    result.extend_from_slice(&haystack[position..offset]);
+                    result.extend_from_slice(replacement);
+                    position = offset + self.pattern.len();
+                    replaced += 1;
+                },
+                None => break,
+            }
+        }
+        result.extend_from_slice(&haystack[position..]);
+        return result;
+    }
+
+    ///
+    /// Copies `reader` into `writer`, substituting every non-overlapping
+    /// occurrence of the pattern. Only a chunk of the input plus the pattern
+    /// length is held in memory at a time.
+    /// # Arguments
+    /// * `reader` - where to read the original contents from
+    /// * `writer` - where to write the replaced contents to
+    /// * `replacement` - what every occurrence is replaced with
+    ///
+    /// # Result
+    /// The number of occurrences that were replaced
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    /// use std::io::Cursor;
+    ///
+    /// let mut output: Vec<u8> = Vec::new();
+    /// let count = Searcher::new("cat")
+    ///     .replace_to_writer(Cursor::new("cat and cat"), &mut output, b"dog")
+    ///     .unwrap();
+    /// assert_eq!(2, count);
+    /// assert_eq!(b"dog and dog".to_vec(), output);
+    /// ```
+    ///
+    pub fn replace_to_writer<R: Read, W: Write>(&self, mut reader: R, mut writer: W,
+                                                replacement: &[u8]) -> io::Result<usize> {
+        let m = self.pattern.len();
+        if m == 0 {
+            io::copy(&mut reader, &mut writer)?;
+            return Ok(0);
+        }
+
+        // the bytes after a match that the conditions may need to see
+        let context = if self.has_conditions() { CONTEXT } else { 0 };
+        let mut count = 0;
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        // the start of the bytes not yet written; the ones before it are
+        // kept for the conditions to look back at
+        let mut position = 0;
+        let mut finished = false;
+
+        while !finished {
+            let read = match reader.read(&mut chunk) {
+                Ok(read) => read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            finished = read == 0;
+            buffer.extend_from_slice(&chunk[..read]);
+
+            // a match closer to the end than this may be turned into a
+            // non-match by the next chunk, so it waits for it
+            let limit = if finished { buffer.len() } else { buffer.len().saturating_sub(context) };
+            while let Some(offset) = self.find_at(&buffer, position) {
+                if offset + m > limit {
+                    break;
+                }
+                writer.write_all(&buffer[position..offset])?;
+                writer.write_all(replacement)?;
+                position = offset + m;
+                count += 1;
+            }
+
+            // the last `m - 1` bytes before the limit could be the start of
+            // a match that is completed by the next chunk, so they are held
+            // back
+            let keep = if finished {
+                buffer.len()
+            } else {
+                cmp::max(position, limit.saturating_sub(m - 1))
+            };
+            writer.write_all(&buffer[position..keep])?;
+            let drop = keep.saturating_sub(context);
+            buffer.drain(..drop);
+            position = keep - drop;
+        }
+
+        writer.flush()?;
+        return Ok(count);
+    }
+}
+
+///
+/// A Boyer-Moore searcher that scans from the end of the haystack towards
+/// the start. The shift tables are those of the reversed pattern, which is
+/// matched against the haystack read backwards.
+///
+/// ```
+/// use boyer_moore::search::searcher::ReverseSearcher;
+///
+/// let searcher = ReverseSearcher::new("ERROR");
+/// let log = "ERROR one\nINFO two\nERROR three\n";
+/// assert_eq!(Some(19), searcher.rfind(log));
+/// assert_eq!(vec![19, 0], searcher.rfind_iter(log).collect::<Vec<usize>>());
+/// ```
+///
+#[derive(Clone)]
+pub struct ReverseSearcher {
+    reversed: Searcher,
+}
+
+impl ReverseSearcher {
+
+    ///
+    /// Builds the reverse searcher for the given pattern.
+    ///
+    pub fn new(pattern: &str) -> ReverseSearcher {
+        return ReverseSearcher::from_bytes(pattern.as_bytes());
+    }
+
+    ///
+    /// Builds the reverse searcher for a pattern that is not necessarily UTF-8.
+    ///
+    pub fn from_bytes(pattern: &[u8]) -> ReverseSearcher {
+        let reversed: Vec<u8> = pattern.iter().cloned().rev().collect();
+        return ReverseSearcher { reversed: Searcher::from_bytes(&reversed) };
+    }
+
+    /// The length in bytes of the pattern this searcher looks for.
+    pub fn pattern_len(&self) -> usize {
+        return self.reversed.pattern.len();
+    }
+
+    /// The pattern this searcher looks for, in its original order.
+    pub(crate) fn pattern(&self) -> Vec<u8> {
+        return self.reversed.pattern.iter().cloned().rev().collect();
+    }
+
+    ///
+    /// Returns the offset of the last occurrence of the pattern.
+    ///
+    pub fn rfind<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H) -> Option<usize> {
+        let haystack = haystack.as_ref();
+        return self.rfind_at(haystack, haystack.len());
+    }
+
+    ///
+    /// Returns the offset of the last occurrence of the pattern that ends at
+    /// or before `end`.
+    /// # Arguments
+    /// * `haystack` - the body to search in
+    /// * `end` - the offset to search backwards from
+    ///
+    pub fn rfind_at(&self, haystack: &[u8], end: usize) -> Option<usize> {
+        if end > haystack.len() {
+            return None;
+        }
+        let m = self.pattern_len();
+        // position `i` of the reversed haystack is `end - 1 - i` of the
+        // original, where the word and line conditions are checked
+        return self.reversed.scan(end, 0, |i| haystack[end - 1 - i],
+                                  |i| self.reversed.accepts(haystack, end - i - m))
+            .map(|i| end - i - m);
+    }
+
+    ///
+    /// Returns the offsets of every non-overlapping occurrence of the
+    /// pattern, starting with the last.
+    ///
+    pub fn rfind_iter<'a, H: AsRef<[u8]> + ?Sized>(&'a self, haystack: &'a H) -> RFindIter<'a> {
+        let haystack = haystack.as_ref();
+        return RFindIter { searcher: self, haystack: haystack, end: Some(haystack.len()) };
+    }
+}
+
+impl Searcher {
+
+    ///
+    /// Builds the searcher that finds this pattern from the end of the haystack,
+    /// with the same word and line conditions.
+    ///
+    pub fn reverse(&self) -> ReverseSearcher {
+        let mut reverse = ReverseSearcher::from_bytes(&self.pattern);
+        reverse.reversed = reverse.reversed.whole_word(self.whole_word)
+            .line_start(self.line_start)
+            .line_end(self.line_end);
+        return reverse;
+    }
+}
+
+///
+/// Iterator over the offsets of the occurrences of a pattern.
+///
+pub struct FindIter<'a> {
+    searcher: &'a Searcher,
+    haystack: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for FindIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        match self.searcher.find_at(self.haystack, self.position) {
+            Some(offset) => {
+                // no other occurrence can start within a period of this one
+                self.position = offset + match self.searcher.kind {
+                    MatchKind::Overlapping => self.searcher.period,
+                    MatchKind::NonOverlapping => self.searcher.pattern.len(),
+                };
+                return Some(offset);
+            },
+            None => {
+                self.position = self.haystack.len() + 1;
+                return None;
+            },
+        }
+    }
+}
+
+///
+/// Iterator over the offsets of the non-overlapping occurrences of a pattern,
+/// from the end of the haystack to the start.
+///
+pub struct RFindIter<'a> {
+    searcher: &'a ReverseSearcher,
+    haystack: &'a [u8],
+    end: Option<usize>,
+}
+
+impl<'a> Iterator for RFindIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let found = self.end.and_then(|end| self.searcher.rfind_at(self.haystack, end));
+        self.end = found;
+        return found;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+    use std::io::Cursor;
+
+    /// Reader that hands out at most `step` bytes per call, to exercise
+    /// matches that straddle chunk boundaries.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = *[self.step, buf.len(), self.data.len()].iter().min().unwrap();
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            return Ok(n);
+        }
+    }
+
+    fn naive(pattern: &[u8], haystack: &[u8]) -> Vec<usize> {
+        let mut results = Vec::new();
+        let mut i = 0;
+        while pattern.len() > 0 && i + pattern.len() <= haystack.len() {
+            if &haystack[i..(i + pattern.len())] == pattern {
+                results.push(i);
+                i += pattern.len();
+            } else {
+                i += 1;
+            }
+        }
+        return results;
+    }
+
+    #[test]
+    fn find_simple() {
+        let searcher = Searcher::new("pattern");
+        assert_eq!(Some(2), searcher.find("a pattern to find"));
+        assert_eq!(None, searcher.find("nothing here"));
+    }
+
+    #[test]
+    fn find_at_skips_earlier() {
+        let searcher = Searcher::new("ab");
+        assert_eq!(Some(4), searcher.find_at(b"ab--ab", 1));
+        assert_eq!(None, searcher.find_at(b"ab--ab", 7));
+    }
+
+    #[test]
+    fn empty_pattern_never_matches() {
+        let searcher = Searcher::new("");
+        assert_eq!(None, searcher.find("abc"));
+        assert_eq!("abc", searcher.replace_all("abc", "X"));
+    }
+
+    #[test]
+    fn matches_naive_search() {
+        let mut random = Random::new(7);
+        for _ in 0..300 {
+            let haystack = random.bytes(b"abc", 59);
+            let pattern: Vec<u8> = (0..1 + random.next(4)).map(|_| b"abc"[random.next(3)])
+                .collect();
+            let searcher = Searcher::from_bytes(&pattern);
+            let found: Vec<usize> = searcher.find_iter(&haystack).collect();
+            assert_eq!(naive(&pattern, &haystack), found,
+                       "pattern {:?} in {:?}", pattern, haystack);
+        }
+    }
+
+    #[test]
+    fn rfind_simple() {
+        let searcher = ReverseSearcher::new("pattern");
+        assert_eq!(Some(15), searcher.rfind("a pattern, one pattern"));
+        assert_eq!(None, searcher.rfind("nothing here"));
+        assert_eq!(None, ReverseSearcher::new("").rfind("abc"));
+    }
+
+    #[test]
+    fn whole_word() {
+        let searcher = Searcher::new("test").whole_word(true);
+        assert_eq!(vec![13], searcher.find_iter("latest tests test").collect::<Vec<usize>>());
+        assert_eq!(Some(6), searcher.find("über-test"));
+        assert_eq!(None, searcher.find("testé"));
+        assert_eq!(Some(13), searcher.reverse().rfind("latest tests test"));
+    }
+
+    #[test]
+    fn whole_word_after_start() {
+        // the text before `start` still decides whether a match is a word
+        let searcher = Searcher::new("ab").whole_word(true);
+        assert_eq!(None, searcher.find_at(b"xab", 1));
+    }
+
+    #[test]
+    fn line_anchors() {
+        let text = "an error\nerror: disk\r\nerror";
+        let start = Searcher::new("error").line_start(true);
+        assert_eq!(vec![9, 22], start.find_iter(text).collect::<Vec<usize>>());
+        let end = Searcher::new("error").line_end(true);
+        assert_eq!(vec![3, 22], end.find_iter(text).collect::<Vec<usize>>());
+        let line = Searcher::new("error").whole_line(true);
+        assert_eq!(vec![22], line.find_iter(text).collect::<Vec<usize>>());
+        assert_eq!(Some(22), line.reverse().rfind(text));
+        assert_eq!(Some(3), end.reverse().rfind_at(text.as_bytes(), 20));
+    }
+
+    #[test]
+    fn rejected_matches_keep_overlapping_ones() {
+        let searcher = Searcher::new("aa").line_end(true).match_kind(MatchKind::Overlapping);
+        assert_eq!(vec![2], searcher.find_iter("aaaa").collect::<Vec<usize>>());
+        assert_eq!(vec![2, 5], searcher.find_iter("xaaa\naa").collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn conditions_agree_with_naive_search() {
+        let mut random = Random::new(47);
+        for _ in 0..3000 {
+            let haystack = random.bytes(b"ab \n", 29);
+            let pattern: Vec<u8> = (0..1 + random.next(3)).map(|_| b"ab "[random.next(3)])
+                .collect();
+            let (word, start, end) = (random.next(2) == 1, random.next(2) == 1,
+                                      random.next(2) == 1);
+            let searcher = Searcher::from_bytes(&pattern).whole_word(word)
+                .line_start(start).line_end(end).match_kind(MatchKind::Overlapping);
+
+            let expected: Vec<usize> = (0..(haystack.len() + 1).saturating_sub(pattern.len()))
+                .filter(|&i| haystack[i..].starts_with(&pattern))
+                .filter(|&i| !word || (!boundary::word_before(&haystack, i)
+                                       && !boundary::word_after(&haystack, i + pattern.len())))
+                .filter(|&i| !start || boundary::is_line_start(&haystack, i))
+                .filter(|&i| !end || boundary::is_line_end(&haystack, i + pattern.len()))
+                .collect();
+            assert_eq!(expected, searcher.find_iter(&haystack).collect::<Vec<usize>>(),
+                       "{:?} in {:?}", pattern, haystack);
+            assert_eq!(expected.last().cloned(), searcher.reverse().rfind(&haystack),
+                       "last {:?} in {:?}", pattern, haystack);
+        }
+    }
+
+    #[test]
+    fn rfind_at_limits_end() {
+        let searcher = ReverseSearcher::new("ab");
+        assert_eq!(Some(0), searcher.rfind_at(b"ab--ab", 5));
+        assert_eq!(Some(4), searcher.rfind_at(b"ab--ab", 6));
+        assert_eq!(None, searcher.rfind_at(b"ab--ab", 1));
+    }
+
+    #[test]
+    fn rfind_iter_overlapping_candidates() {
+        let offsets: Vec<usize> = ReverseSearcher::new("aa").rfind_iter("aaaaa").collect();
+        assert_eq!(vec![3, 1], offsets, "non-overlapping from the end");
+    }
+
+    #[test]
+    fn rfind_matches_naive_search() {
+        let mut random = Random::new(11);
+        for _ in 0..300 {
+            let haystack = random.bytes(b"abc", 59);
+            let pattern: Vec<u8> = (0..1 + random.next(4)).map(|_| b"abc"[random.next(3)])
+                .collect();
+
+            let reversed_haystack: Vec<u8> = haystack.iter().cloned().rev().collect();
+            let reversed_pattern: Vec<u8> = pattern.iter().cloned().rev().collect();
+            let expected: Vec<usize> = naive(&reversed_pattern, &reversed_haystack).iter()
+                .map(|i| haystack.len() - i - pattern.len()).collect();
+
+            let searcher = Searcher::from_bytes(&pattern).reverse();
+            let found: Vec<usize> = searcher.rfind_iter(&haystack).collect();
+            assert_eq!(expected, found, "pattern {:?} in {:?}", pattern, haystack);
+        }
+    }
+
+    #[test]
+    fn unicode_offsets_are_bytes() {
+        let searcher = Searcher::new("é");
+        assert_eq!(Some(3), searcher.find("abcé"));
+        assert_eq!("abce", searcher.replace_all("abcé", "e"));
+    }
+
+    #[test]
+    fn replace_all() {
+        let searcher = Searcher::new("jdd");
+        assert_eq!("search X in the string X of X",
+                   searcher.replace_all("search jdd in the string jdd of jdd", "X"));
+    }
+
+    #[test]
+    fn replace_overlapping_candidates() {
+        let searcher = Searcher::new("aa");
+        assert_eq!("bbba", searcher.replace_all("aaaaaaa", "b"), "non-overlapping");
+    }
+
+    #[test]
+    fn overlapping_find_iter() {
+        let searcher = Searcher::new("abcab").match_kind(MatchKind::Overlapping);
+        let offsets: Vec<usize> = searcher.find_iter("abcabcabcab").collect();
+        assert_eq!(vec![0, 3, 6], offsets);
+        assert_eq!("XcX", searcher.replace_all("abcabcabcab", "X"), "replace ignores kind");
+    }
+
+    #[test]
+    fn replacen_zero() {
+        let searcher = Searcher::new("a");
+        assert_eq!("aaa", searcher.replacen("aaa", "b", 0));
+    }
+
+    #[test]
+    fn replace_bytes() {
+        let searcher = Searcher::from_bytes(&[0xff, 0x00]);
+        let result = searcher.replace_all_bytes(&[1, 0xff, 0x00, 2], &[9]);
+        assert_eq!(vec![1, 9, 2], result);
+    }
+
+    #[test]
+    fn replace_to_writer_matches_replace_all() {
+        let input = "one boundary two boundary three boundar".repeat(50);
+        let searcher = Searcher::new("boundary");
+        let expected = searcher.replace_all(&input, "|");
+
+        for step in 1..12 {
+            let mut output: Vec<u8> = Vec::new();
+            let reader = Trickle { data: input.as_bytes(), step: step };
+            let count = searcher.replace_to_writer(reader, &mut output, b"|").unwrap();
+            assert_eq!(100, count, "every match counted with step {}", step);
+            assert_eq!(expected.as_bytes(), &output[..], "same output with step {}", step);
+        }
+    }
+
+    #[test]
+    fn replace_to_writer_with_conditions() {
+        let input = "test latest test\ntests test\r\ntest_case test\n".repeat(20);
+        let searcher = Searcher::new("test").whole_word(true);
+        let expected = searcher.replace_all(&input, "|");
+        let line_searcher = Searcher::new("test").whole_line(true);
+
+        for step in 1..12 {
+            let mut output: Vec<u8> = Vec::new();
+            let reader = Trickle { data: input.as_bytes(), step: step };
+            let count = searcher.replace_to_writer(reader, &mut output, b"|").unwrap();
+            assert_eq!(80, count, "every whole word counted with step {}", step);
+            assert_eq!(expected.as_bytes(), &output[..], "same output with step {}", step);
+
+            let mut output: Vec<u8> = Vec::new();
+            let reader = Trickle { data: input.as_bytes(), step: step };
+            assert_eq!(0, line_searcher.replace_to_writer(reader, &mut output, b"|").unwrap());
+        }
+    }
+
+    #[test]
+    fn replace_to_writer_no_match() {
+        let mut output: Vec<u8> = Vec::new();
+        let count = Searcher::new("zzz")
+            .replace_to_writer(Cursor::new("nothing to see"), &mut output, b"X")
+            .unwrap();
+        assert_eq!(0, count);
+        assert_eq!(b"nothing to see".to_vec(), output);
+    }
+}
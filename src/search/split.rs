@@ -0,0 +1,312 @@
+//!
+//! Iterators that split text on every occurrence of a pattern, following the
+//! semantics of the standard library's `str::split` family.
+//!
+//! ```
+//! use boyer_moore::search::searcher::Searcher;
+//!
+//! let searcher = Searcher::new("\r\n--boundary");
+//! let parts: Vec<&str> = searcher.split("one\r\n--boundarytwo\r\n--boundary").collect();
+//! assert_eq!(vec!["one", "two", ""], parts);
+//! ```
+
+use search::searcher::ReverseSearcher;
+use search::searcher::Searcher;
+
+///
+/// Something that can be searched as bytes and sliced at the offsets of the
+/// matches. Implemented for `str` and `[u8]`.
+///
+pub trait Haystack {
+    /// The bytes that are searched.
+    fn as_bytes(&self) -> &[u8];
+
+    /// The part of the haystack between the two byte offsets.
+    fn slice(&self, start: usize, end: usize) -> &Self;
+
+    ///
+    /// Whether every match of the pattern falls where the haystack can be
+    /// sliced. A `str` can only be cut at character boundaries, which a
+    /// pattern that is not valid UTF-8 may match inside of.
+    ///
+    fn splits_on(_pattern: &[u8]) -> bool {
+        return true;
+    }
+}
+
+impl Haystack for str {
+    fn as_bytes(&self) -> &[u8] {
+        return str::as_bytes(self);
+    }
+
+    fn slice(&self, start: usize, end: usize) -> &str {
+        return &self[start..end];
+    }
+
+    fn splits_on(pattern: &[u8]) -> bool {
+        return ::std::str::from_utf8(pattern).is_ok();
+    }
+}
+
+impl Haystack for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        return self;
+    }
+
+    fn slice(&self, start: usize, end: usize) -> &[u8] {
+        return &self[start..end];
+    }
+}
+
+impl Searcher {
+
+    ///
+    /// Iterates over the pieces of the haystack between the matches.
+    ///
+    /// # Panics
+    /// If the haystack is a `str` and the pattern is not valid UTF-8.
+    ///
+    pub fn split<'s, 'h, H: Haystack + ?Sized>(&'s self, haystack: &'h H) -> Split<'s, 'h, H> {
+        return Split::new(self, haystack, usize::MAX, false);
+    }
+
+    ///
+    /// Like `split`, but yields at most `count` pieces, the last of which is
+    /// the rest of the haystack.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let parts: Vec<&str> = Searcher::new(", ").splitn("a, b, c", 2).collect();
+    /// assert_eq!(vec!["a", "b, c"], parts);
+    /// ```
+    ///
+    /// # Panics
+    /// If the haystack is a `str` and the pattern is not valid UTF-8.
+    ///
+    pub fn splitn<'s, 'h, H: Haystack + ?Sized>(&'s self, haystack: &'h H, count: usize)
+                                            -> Split<'s, 'h, H> {
+        return Split::new(self, haystack, count, false);
+    }
+
+    ///
+    /// Like `split`, but each piece keeps the match that terminated it.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let lines: Vec<&str> = Searcher::new("\r\n").split_inclusive("a\r\nb\r\n").collect();
+    /// assert_eq!(vec!["a\r\n", "b\r\n"], lines);
+    /// ```
+    ///
+    /// # Panics
+    /// If the haystack is a `str` and the pattern is not valid UTF-8.
+    ///
+    pub fn split_inclusive<'s, 'h, H: Haystack + ?Sized>(&'s self, haystack: &'h H)
+                                                     -> Split<'s, 'h, H> {
+        return Split::new(self, haystack, usize::MAX, true);
+    }
+}
+
+impl ReverseSearcher {
+
+    ///
+    /// Iterates over the pieces of the haystack between the matches, starting
+    /// from the end. Matches are found right to left, so for self-overlapping
+    /// patterns the pieces can differ from those of `Searcher::split` in
+    /// reverse.
+    ///
+    /// ```
+    /// use boyer_moore::search::searcher::Searcher;
+    ///
+    /// let reversed = Searcher::new("::").reverse();
+    /// let parts: Vec<&str> = reversed.rsplit("a::b::c").collect();
+    /// assert_eq!(vec!["c", "b", "a"], parts);
+    /// ```
+    ///
+    /// # Panics
+    /// If the haystack is a `str` and the pattern is not valid UTF-8.
+    ///
+    pub fn rsplit<'s, 'h, H: Haystack + ?Sized>(&'s self, haystack: &'h H) -> RSplit<'s, 'h, H> {
+        assert!(H::splits_on(&self.pattern()), "a str can only be split on a UTF-8 pattern");
+        return RSplit { searcher: self, haystack: haystack, end: haystack.as_bytes().len(),
+                        finished: false };
+    }
+}
+
+///
+/// Iterator over the pieces of a haystack between matches, front to back.
+///
+pub struct Split<'s, 'h, H: ?Sized + 'h> {
+    searcher: &'s Searcher,
+    haystack: &'h H,
+    position: usize,
+    remaining: usize,
+    inclusive: bool,
+    finished: bool,
+}
+
+impl<'s, 'h, H: Haystack + ?Sized> Split<'s, 'h, H> {
+    fn new(searcher: &'s Searcher, haystack: &'h H, count: usize, inclusive: bool)
+           -> Split<'s, 'h, H> {
+        assert!(H::splits_on(searcher.pattern()), "a str can only be split on a UTF-8 pattern");
+        return Split { searcher: searcher, haystack: haystack, position: 0, remaining: count,
+                       inclusive: inclusive, finished: false };
+    }
+}
+
+impl<'s, 'h, H: Haystack + ?Sized> Iterator for Split<'s, 'h, H> {
+    type Item = &'h H;
+
+    fn next(&mut self) -> Option<&'h H> {
+        if self.finished {
+            return None;
+        }
+
+        let len = self.haystack.as_bytes().len();
+        let start = self.position;
+        if self.remaining == 0 {
+            self.finished = true;
+            return None;
+        } else if self.remaining == 1 {
+            self.finished = true;
+            return Some(self.haystack.slice(start, len));
+        }
+
+        match self.searcher.find_at(self.haystack.as_bytes(), start) {
+            Some(offset) => {
+                let match_end = offset + self.searcher.pattern().len();
+                let end = if self.inclusive { match_end } else { offset };
+                self.position = match_end;
+                self.remaining -= 1;
+                return Some(self.haystack.slice(start, end));
+            },
+            None => {
+                self.finished = true;
+                if self.inclusive && start == len {
+                    return None;
+                }
+                return Some(self.haystack.slice(start, len));
+            },
+        }
+    }
+}
+
+///
+/// Iterator over the pieces of a haystack between matches, back to front.
+///
+pub struct RSplit<'s, 'h, H: ?Sized + 'h> {
+    searcher: &'s ReverseSearcher,
+    haystack: &'h H,
+    end: usize,
+    finished: bool,
+}
+
+impl<'s, 'h, H: Haystack + ?Sized> Iterator for RSplit<'s, 'h, H> {
+    type Item = &'h H;
+
+    fn next(&mut self) -> Option<&'h H> {
+        if self.finished {
+            return None;
+        }
+
+        let end = self.end;
+        match self.searcher.rfind_at(self.haystack.as_bytes(), end) {
+            Some(offset) => {
+                self.end = offset;
+                return Some(self.haystack.slice(offset + self.searcher.pattern_len(), end));
+            },
+            None => {
+                self.finished = true;
+                return Some(self.haystack.slice(0, end));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use search::searcher::Searcher;
+    use search::test_util::Random;
+
+    #[test]
+    fn split_bytes() {
+        let searcher = Searcher::from_bytes(b"--");
+        let parts: Vec<&[u8]> = searcher.split(&b"a--b----c"[..]).collect();
+        assert_eq!(vec![&b"a"[..], &b"b"[..], &b""[..], &b"c"[..]], parts);
+    }
+
+    #[test]
+    fn split_empty_haystack() {
+        let searcher = Searcher::new(",");
+        assert_eq!(vec![""], searcher.split("").collect::<Vec<&str>>());
+        assert_eq!(vec![""], searcher.reverse().rsplit("").collect::<Vec<&str>>());
+        assert_eq!(0, searcher.split_inclusive("").count());
+    }
+
+    #[test]
+    #[should_panic(expected = "a str can only be split on a UTF-8 pattern")]
+    fn split_str_on_invalid_pattern() {
+        Searcher::from_bytes(b"\xa9").split("caf\u{e9}");
+    }
+
+    #[test]
+    #[should_panic(expected = "a str can only be split on a UTF-8 pattern")]
+    fn rsplit_str_on_invalid_pattern() {
+        Searcher::from_bytes(b"\xa9").reverse().rsplit("caf\u{e9}");
+    }
+
+    #[test]
+    fn split_bytes_on_invalid_pattern() {
+        let searcher = Searcher::from_bytes(b"\xa9");
+        let parts: Vec<&[u8]> = searcher.split("caf\u{e9}".as_bytes()).collect();
+        assert_eq!(vec![&b"caf\xc3"[..], &b""[..]], parts);
+    }
+
+    #[test]
+    fn splitn_zero() {
+        assert_eq!(0, Searcher::new(",").splitn("a,b", 0).count());
+    }
+
+    #[test]
+    fn rsplit_overlapping() {
+        let searcher = Searcher::new("aa");
+        assert_eq!(vec!["", "a"], searcher.reverse().rsplit("aaa").collect::<Vec<&str>>());
+        assert_eq!(vec!["", "a"], searcher.split("aaa").collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn matches_standard_library() {
+        let mut random = Random::new(42);
+        for _ in 0..500 {
+            let haystack = random.string(&["a", "b", "é"], 20);
+            let pattern = random.string(&["a", "b", "é"], 3);
+            if pattern.is_empty() {
+                continue;
+            }
+            let searcher = Searcher::new(&pattern);
+            let reversed = searcher.reverse();
+            let context = format!("{:?} in {:?}", pattern, haystack);
+
+            assert_eq!(haystack.split(pattern.as_str()).collect::<Vec<&str>>(),
+                       searcher.split(haystack.as_str()).collect::<Vec<&str>>(),
+                       "split {}", context);
+            assert_eq!(haystack.rsplit(pattern.as_str()).collect::<Vec<&str>>(),
+                       reversed.rsplit(haystack.as_str()).collect::<Vec<&str>>(),
+                       "rsplit {}", context);
+            assert_eq!(haystack.split_inclusive(pattern.as_str()).collect::<Vec<&str>>(),
+                       searcher.split_inclusive(haystack.as_str()).collect::<Vec<&str>>(),
+                       "split_inclusive {}", context);
+            for n in 0..5 {
+                assert_eq!(haystack.splitn(n, pattern.as_str()).collect::<Vec<&str>>(),
+                           searcher.splitn(haystack.as_str(), n).collect::<Vec<&str>>(),
+                           "splitn {} {}", n, context);
+            }
+
+            let bytes: Vec<&[u8]> = searcher.split(haystack.as_bytes()).collect();
+            let expected: Vec<&[u8]> = haystack.split(pattern.as_str())
+                .map(|s| s.as_bytes()).collect();
+            assert_eq!(expected, bytes, "split bytes {}", context);
+        }
+    }
+}
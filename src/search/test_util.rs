@@ -0,0 +1,40 @@
+//!
+//! Helpers shared by the randomized tests of the search modules.
+//!
+
+///
+/// Deterministic pseudo random numbers, so that a failing randomized test
+/// fails the same way every time it is run.
+///
+pub(crate) struct Random {
+    seed: u32,
+}
+
+impl Random {
+
+    /// Starts the sequence from the given seed.
+    pub(crate) fn new(seed: u32) -> Random {
+        return Random { seed: seed };
+    }
+
+    /// The next number, below `bound`.
+    pub(crate) fn next(&mut self, bound: usize) -> usize {
+        self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+        return (self.seed >> 16) as usize % bound;
+    }
+
+    ///
+    /// A string of at most `max_len` pieces, each picked from the alphabet.
+    /// Tiny alphabets make patterns match often and overlap with themselves.
+    ///
+    pub(crate) fn string(&mut self, alphabet: &[&str], max_len: usize) -> String {
+        let len = self.next(max_len + 1);
+        return (0..len).map(|_| alphabet[self.next(alphabet.len())]).collect();
+    }
+
+    /// Like `string`, but of bytes.
+    pub(crate) fn bytes(&mut self, alphabet: &[u8], max_len: usize) -> Vec<u8> {
+        let len = self.next(max_len + 1);
+        return (0..len).map(|_| alphabet[self.next(alphabet.len())]).collect();
+    }
+}
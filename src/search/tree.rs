@@ -1,5 +1,14 @@
 
+use std::borrow::Borrow;
+use std::cmp::max;
 use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::iter::FromIterator;
+use std::ops::Bound;
+use std::ops::Index;
+use std::ops::RangeBounds;
 
 impl<K: Ord, V> Tree<K, V> {
 
@@ -11,7 +20,7 @@ impl<K: Ord, V> Tree<K, V> {
     ///
     /// let mut tree: tree::Tree<i32, i32> = tree::Tree::new();
     /// let _ = tree.insert(5, 6);
-    /// assert_eq!(Some(&6), tree.get(5));
+    /// assert_eq!(Some(&6), tree.get(&5));
     /// ```
     pub fn new() -> Tree<K, V> {
         return Tree { root: None, size: 0 }
@@ -29,105 +38,1203 @@ impl<K: Ord, V> Tree<K, V> {
         return result;
     }
 
-    pub fn get(&self, key: K) -> Option<&V> {
-        return Tree::get_value(&self.root, key);        
+    ///
+    /// Returns the value for the key. The key may be any borrowed form of the
+    /// tree's key type, so a `Tree<String, _>` can be queried with a `&str`.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    ///
+    /// let mut tree: tree::Tree<String, usize> = tree::Tree::new();
+    /// tree.insert("needle".to_string(), 3);
+    /// assert_eq!(Some(&3), tree.get("needle"));
+    /// assert!(!tree.contains_key("haystack"));
+    /// ```
+    ///
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q> {
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Greater => node = &n.right,
+                Ordering::Equal => return Some(&n.value),
+            }
+        }
+        return None;
+    }
+
+    ///
+    /// Returns a mutable reference to the value for the key.
+    ///
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q> {
+        return self.node_mut(key).map(|node| &mut node.value);
+    }
+
+    ///
+    /// Returns whether the key is in the tree.
+    ///
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q> {
+        return self.get(key).is_some();
+    }
+
+    ///
+    /// Returns the entry for the key, for updating or inserting its value in
+    /// place.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    ///
+    /// let mut counts: tree::Tree<&str, usize> = tree::Tree::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     counts.entry(word).and_modify(|count| *count += 1).or_insert(1);
+    /// }
+    /// assert_eq!(Some(&3), counts.get("a"));
+    /// assert_eq!(Some(&1), counts.get("c"));
+    /// ```
+    ///
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        if !self.contains_key(&key) {
+            return Entry::Vacant(VacantEntry { tree: self, key: key });
+        }
+        let node = self.node_mut(&key).unwrap();
+        let Node { ref key, ref mut value, .. } = *node;
+        return Entry::Occupied(OccupiedEntry { key: key, value: value });
+    }
+
+    fn node_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut Node<K, V>>
+        where K: Borrow<Q> {
+        let mut node = &mut self.root;
+        loop {
+            match *node {
+                None => return None,
+                Some(ref mut n) => match key.cmp(n.key.borrow()) {
+                    Ordering::Less => node = &mut n.left,
+                    Ordering::Greater => node = &mut n.right,
+                    Ordering::Equal => return Some(n),
+                },
+            }
+        }
+    }
+
+    ///
+    /// Returns the key-value pair where the key is the largest value less than or
+    /// equal to the input key. If there is no key less than the input key, then
+    /// None is returned.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    /// let mut tree: tree::Tree<i32, String> = tree::Tree::new();
+    /// tree.insert(5, "five".to_string());
+    /// tree.insert(4, "four".to_string());
+    /// tree.insert(3, "three".to_string());
+    /// tree.insert(8, "eight".to_string());
+    ///
+    /// assert_eq!(Some((&8, &"eight".to_string())), tree.lower_bound(&10));
+    /// assert_eq!(None, tree.lower_bound(&2));
+    /// ```
+    ///
+    pub fn lower_bound<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)> where K: Borrow<Q> {
+        return self.floor_node(key, true).map(Node::entry);
+    }
+
+    ///
+    /// Returns the key-value pair where the key is the smallest value greater
+    /// than or equal to the input key (its ceiling). If there is no key greater
+    /// than the input key, then None is returned.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    /// let mut tree: tree::Tree<i32, &str> = tree::Tree::new();
+    /// tree.insert(5, "five");
+    /// tree.insert(8, "eight");
+    ///
+    /// assert_eq!(Some((&8, &"eight")), tree.upper_bound(&6));
+    /// assert_eq!(Some((&5, &"five")), tree.upper_bound(&5));
+    /// assert_eq!(None, tree.upper_bound(&9));
+    /// ```
+    ///
+    pub fn upper_bound<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)> where K: Borrow<Q> {
+        return self.ceiling_node(key, true).map(Node::entry);
+    }
+
+    ///
+    /// Returns the entry with the smallest key strictly greater than the input key.
+    ///
+    pub fn successor<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)> where K: Borrow<Q> {
+        return self.ceiling_node(key, false).map(Node::entry);
+    }
+
+    ///
+    /// Returns the entry with the largest key strictly less than the input key.
+    ///
+    pub fn predecessor<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)> where K: Borrow<Q> {
+        return self.floor_node(key, false).map(Node::entry);
+    }
+
+    ///
+    /// Returns the entry with the smallest key.
+    ///
+    pub fn first(&self) -> Option<(&K, &V)> {
+        return self.first_node().map(Node::entry);
+    }
+
+    ///
+    /// Returns the entry with the largest key.
+    ///
+    pub fn last(&self) -> Option<(&K, &V)> {
+        return self.last_node().map(Node::entry);
+    }
+
+    ///
+    /// Returns the number of keys in the tree that are less than the input key,
+    /// which is also the position the key has or would have in sorted order.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    /// let mut tree: tree::Tree<i32, i32> = tree::Tree::new();
+    /// for i in vec![10, 20, 30] {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// assert_eq!(1, tree.rank(&20));
+    /// assert_eq!(2, tree.rank(&25));
+    /// assert_eq!(Some((&30, &30)), tree.select(2));
+    /// ```
+    ///
+    pub fn rank<Q: Ord + ?Sized>(&self, key: &Q) -> usize where K: Borrow<Q> {
+        let mut rank = 0;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Greater => {
+                    rank += subtree_size(&n.left) + 1;
+                    node = &n.right;
+                },
+                Ordering::Equal => return rank + subtree_size(&n.left),
+            }
+        }
+        return rank;
+    }
+
+    ///
+    /// Returns the entry at position `index` in sorted order, counting from
+    /// zero, or None if the tree has no more than `index` entries.
+    ///
+    pub fn select(&self, mut index: usize) -> Option<(&K, &V)> {
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            let left = subtree_size(&n.left);
+            match index.cmp(&left) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some(n.entry()),
+                Ordering::Greater => {
+                    index -= left + 1;
+                    node = &n.right;
+                },
+            }
+        }
+        return None;
+    }
+
+    /// The node with the given index in ascending key order.
+    fn select_node_mut(&mut self, mut index: usize) -> Option<&mut Node<K, V>> {
+        let mut node = &mut self.root;
+        loop {
+            match *node {
+                None => return None,
+                Some(ref mut n) => {
+                    let left = subtree_size(&n.left);
+                    match index.cmp(&left) {
+                        Ordering::Less => node = &mut n.left,
+                        Ordering::Equal => return Some(n),
+                        Ordering::Greater => {
+                            index -= left + 1;
+                            node = &mut n.right;
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    ///
+    /// Returns a cursor positioned at the given key, or None if the key is
+    /// not in the tree.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    /// let mut tree: tree::Tree<i32, i32> = tree::Tree::new();
+    /// for i in vec![10, 20, 30] {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let mut cursor = tree.cursor(&20).unwrap();
+    /// assert_eq!(Some((&30, &30)), cursor.move_next());
+    /// assert_eq!(None, cursor.move_next());
+    /// assert_eq!(Some((&10, &10)), cursor.move_next(), "wraps around past the end");
+    /// assert_eq!(None, cursor.move_prev());
+    /// ```
+    ///
+    pub fn cursor<Q: Ord + ?Sized>(&self, key: &Q) -> Option<Cursor<'_, K, V>>
+        where K: Borrow<Q> {
+        return self.ceiling_node(key, true)
+            .filter(|node| node.key.borrow() == key)
+            .map(|node| Cursor { tree: self, current: Some(node) });
+    }
+
+    ///
+    /// Returns a cursor positioned at the smallest key.
+    ///
+    pub fn cursor_first(&self) -> Cursor<'_, K, V> {
+        return Cursor { tree: self, current: self.first_node() };
+    }
+
+    ///
+    /// Returns a cursor positioned at the largest key.
+    ///
+    pub fn cursor_last(&self) -> Cursor<'_, K, V> {
+        return Cursor { tree: self, current: self.last_node() };
+    }
+
+    ///
+    /// The node with the largest key less than the input key, or equal to it
+    /// when `inclusive` is set.
+    ///
+    fn floor_node<Q: Ord + ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&Node<K, V>>
+        where K: Borrow<Q> {
+        let mut result = None;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Equal if inclusive => return Some(n),
+                Ordering::Less | Ordering::Equal => node = &n.left,
+                Ordering::Greater => {
+                    // best candidate so far, but a closer one may be to the right
+                    result = Some(&**n);
+                    node = &n.right;
+                },
+            }
+        }
+        return result;
+    }
+
+    ///
+    /// The node with the smallest key greater than the input key, or equal to
+    /// it when `inclusive` is set.
+    ///
+    fn ceiling_node<Q: Ord + ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&Node<K, V>>
+        where K: Borrow<Q> {
+        let mut result = None;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Equal if inclusive => return Some(n),
+                Ordering::Greater | Ordering::Equal => node = &n.right,
+                Ordering::Less => {
+                    // best candidate so far, but a closer one may be to the left
+                    result = Some(&**n);
+                    node = &n.left;
+                },
+            }
+        }
+        return result;
+    }
+
+    fn first_node(&self) -> Option<&Node<K, V>> {
+        let mut node = match self.root {
+            Some(ref n) => n,
+            None => return None,
+        };
+        while let Some(ref left) = node.left {
+            node = left;
+        }
+        return Some(node);
+    }
+
+    fn last_node(&self) -> Option<&Node<K, V>> {
+        let mut node = match self.root {
+            Some(ref n) => n,
+            None => return None,
+        };
+        while let Some(ref right) = node.right {
+            node = right;
+        }
+        return Some(node);
+    }
+
+    ///
+    /// Removes the key from the tree, returning its value if it was present.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    ///
+    /// let mut tree: tree::Tree<i32, &str> = tree::Tree::new();
+    /// tree.insert(1, "one");
+    /// assert_eq!(Some("one"), tree.remove(&1));
+    /// assert_eq!(None, tree.remove(&1));
+    /// assert_eq!(0, tree.size());
+    /// ```
+    ///
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q> {
+        let result = Tree::remove_node(&mut self.root, key);
+        if result.is_some() {
+            self.size -= 1;
+        }
+        return result.map(|(_, value)| value);
+    }
+
+    ///
+    /// Removes and returns the entry with the smallest key.
+    ///
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        if self.root.is_none() {
+            return None;
+        }
+        self.size -= 1;
+        let node = remove_min(&mut self.root);
+        return Some((node.key, node.value));
+    }
+
+    ///
+    /// Removes and returns the entry with the largest key.
+    ///
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        if self.root.is_none() {
+            return None;
+        }
+        self.size -= 1;
+        let node = remove_max(&mut self.root);
+        return Some((node.key, node.value));
+    }
+
+    ///
+    /// Removes every entry from the tree.
+    ///
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.size = 0;
+    }
+
+    ///
+    /// Iterates over the entries in ascending key order.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    ///
+    /// let mut tree: tree::Tree<i32, char> = tree::Tree::new();
+    /// tree.insert(2, 'b');
+    /// tree.insert(1, 'a');
+    /// tree.insert(3, 'c');
+    ///
+    /// let entries: Vec<(&i32, &char)> = tree.iter().collect();
+    /// assert_eq!(vec![(&1, &'a'), (&2, &'b'), (&3, &'c')], entries);
+    /// ```
+    ///
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new(), remaining: self.size };
+        iter.push_left(&self.root);
+        return iter;
+    }
+
+    ///
+    /// Iterates over the entries in ascending key order, with mutable access
+    /// to the values.
+    ///
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut { stack: Vec::new(), remaining: self.size };
+        iter.push_left(&mut self.root);
+        return iter;
+    }
+
+    ///
+    /// Iterates over the keys in ascending order.
+    ///
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        return Keys { inner: self.iter() };
+    }
+
+    ///
+    /// Iterates over the values in ascending order of their keys.
+    ///
+    pub fn values(&self) -> Values<'_, K, V> {
+        return Values { inner: self.iter() };
+    }
+
+    ///
+    /// Iterates in ascending order over the entries whose keys fall within
+    /// the range, such as every match between two offsets.
+    ///
+    /// ```
+    /// use boyer_moore::search::tree;
+    ///
+    /// let mut tree: tree::Tree<usize, usize> = tree::Tree::new();
+    /// for offset in vec![3, 10, 18, 25, 40] {
+    ///     tree.insert(offset, offset * 2);
+    /// }
+    ///
+    /// let keys: Vec<&usize> = tree.range(10..25).map(|(k, _)| k).collect();
+    /// assert_eq!(vec![&10, &18], keys);
+    /// assert_eq!(2, tree.range(..=10).count());
+    /// ```
+    ///
+    /// # Panics
+    /// If the start of the range is after its end, or if both are equal and
+    /// excluded, like `BTreeMap::range`.
+    ///
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+        where K: Borrow<Q>, Q: Ord + ?Sized, R: RangeBounds<Q> {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) if start == end =>
+                panic!("range start and end are equal and excluded in tree"),
+            (Bound::Included(start), Bound::Included(end)) |
+            (Bound::Included(start), Bound::Excluded(end)) |
+            (Bound::Excluded(start), Bound::Included(end)) |
+            (Bound::Excluded(start), Bound::Excluded(end)) if start > end =>
+                panic!("range start is greater than range end in tree"),
+            _ => {},
+        }
+
+        let mut stack = Vec::new();
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => n.key.borrow() >= start,
+                Bound::Excluded(start) => n.key.borrow() > start,
+                Bound::Unbounded => true,
+            };
+            if above_start {
+                stack.push(&**n);
+                node = &n.left;
+            } else {
+                node = &n.right;
+            }
+        }
+
+        // the range ends at the largest key within the end bound
+        let mut last = None;
+        let mut node = &self.root;
+        while let Some(ref n) = *node {
+            let below_end = match range.end_bound() {
+                Bound::Included(end) => n.key.borrow() <= end,
+                Bound::Excluded(end) => n.key.borrow() < end,
+                Bound::Unbounded => true,
+            };
+            if below_end {
+                last = Some(&n.key);
+                node = &n.right;
+            } else {
+                node = &n.left;
+            }
+        }
+
+        return Range { inner: Iter { stack: stack, remaining: self.size }, last: last };
+    }
+
+    fn remove_node<Q: Ord + ?Sized>(node: &mut Link<K, V>, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q> {
+        let ordering = match *node {
+            None => return None,
+            Some(ref n) => key.cmp(n.key.borrow()),
+        };
+
+        let removed = match ordering {
+            Ordering::Less => Tree::remove_node(&mut node.as_mut().unwrap().left, key),
+            Ordering::Greater => Tree::remove_node(&mut node.as_mut().unwrap().right, key),
+            Ordering::Equal => {
+                let mut removed = node.take().unwrap();
+                *node = match (removed.left.take(), removed.right.take()) {
+                    (None, right) => right,
+                    (left, None) => left,
+                    (left, right) => {
+                        // the smallest key of the right subtree takes the place
+                        // of the removed node
+                        let mut right = right;
+                        let mut successor = remove_min(&mut right);
+                        successor.left = left;
+                        successor.right = right;
+                        Some(successor)
+                    },
+                };
+                Some((removed.key, removed.value))
+            },
+        };
+        rebalance(node);
+        return removed;
+    }
+
+    ///
+    /// Inserts into the subtree, returning whether the key was already present.
+    ///
+    fn insert_node(node: &mut Link<K, V>, key: K, value: V) -> bool {
+        let result = match *node {
+            None => {
+                *node = Some(Box::new(Node::new(key, value)));
+                return false;
+            },
+            Some(ref mut node) => {
+                match key.cmp(&node.key) {
+                    Ordering::Less => Tree::insert_node(&mut node.left, key, value),
+                    Ordering::Greater => Tree::insert_node(&mut node.right, key, value),
+                    Ordering::Equal => {
+                        node.key = key;
+                        node.value = value;
+                        return true;
+                    },
+                }
+            },
+        };
+        rebalance(node);
+        return result;
+    }
+}
+
+impl<K: Ord, V> Default for Tree<K, V> {
+    fn default() -> Tree<K, V> {
+        return Tree::new();
+    }
+}
+
+impl<K: Ord + fmt::Debug, V: fmt::Debug> fmt::Debug for Tree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_map().entries(self.iter()).finish();
+    }
+}
+
+/// Trees are equal when they hold the same entries, however they are shaped.
+impl<K: Ord, V: PartialEq> PartialEq for Tree<K, V> {
+    fn eq(&self, other: &Tree<K, V>) -> bool {
+        return self.size == other.size && self.iter().eq(other.iter());
+    }
+}
+
+impl<K: Ord, V: Eq> Eq for Tree<K, V> {}
+
+impl<K: Ord + Hash, V: Hash> Hash for Tree<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        for (key, value) in self.iter() {
+            key.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for Tree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Tree<K, V> {
+        let mut tree = Tree::new();
+        tree.extend(iter);
+        return tree;
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for Tree<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a Tree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        return self.iter();
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut Tree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        return self.iter_mut();
+    }
+}
+
+///
+/// Returns the value for the key.
+///
+/// # Panics
+/// If the key is not in the tree.
+///
+impl<'a, K, Q, V> Index<&'a Q> for Tree<K, V> where K: Ord + Borrow<Q>, Q: Ord + ?Sized {
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        return self.get(key).expect("key not found in tree");
+    }
+}
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+///
+/// A node of an AVL tree. The nodes of `Tree` and of the other balanced trees
+/// in the crate implement this so that they share the rebalancing code.
+///
+pub(crate) trait Balanced: Sized {
+    fn left(&self) -> &Option<Box<Self>>;
+    fn right(&self) -> &Option<Box<Self>>;
+    fn left_mut(&mut self) -> &mut Option<Box<Self>>;
+    fn right_mut(&mut self) -> &mut Option<Box<Self>>;
+
+    /// Height of the subtree rooted at this node, as of the last `update`.
+    fn height(&self) -> usize;
+
+    /// Recomputes everything the node caches about its subtree, including
+    /// its height, after either child has changed.
+    fn update(&mut self);
+}
+
+pub(crate) fn height<N: Balanced>(link: &Option<Box<N>>) -> usize {
+    return match *link {
+        Some(ref node) => node.height(),
+        None => 0,
+    };
+}
+
+fn subtree_size<K, V>(link: &Link<K, V>) -> usize {
+    return match *link {
+        Some(ref node) => node.size,
+        None => 0,
+    };
+}
+
+/// Height of the left subtree minus the height of the right subtree.
+fn balance_factor<N: Balanced>(link: &Option<Box<N>>) -> isize {
+    return match *link {
+        Some(ref node) => height(node.left()) as isize - height(node.right()) as isize,
+        None => 0,
+    };
+}
+
+///
+/// Detaches the node with the smallest key from a non-empty subtree,
+/// rebalancing on the way back up.
+///
+pub(crate) fn remove_min<N: Balanced>(link: &mut Option<Box<N>>) -> Box<N> {
+    let has_left = link.as_ref().expect("removing from an empty subtree").left().is_some();
+    if has_left {
+        let min = remove_min(link.as_mut().unwrap().left_mut());
+        rebalance(link);
+        return min;
+    }
+    let mut min = link.take().unwrap();
+    *link = min.right_mut().take();
+    return min;
+}
+
+///
+/// Detaches the node with the largest key from a non-empty subtree,
+/// rebalancing on the way back up.
+///
+pub(crate) fn remove_max<N: Balanced>(link: &mut Option<Box<N>>) -> Box<N> {
+    let has_right = link.as_ref().expect("removing from an empty subtree").right().is_some();
+    if has_right {
+        let max = remove_max(link.as_mut().unwrap().right_mut());
+        rebalance(link);
+        return max;
+    }
+    let mut max = link.take().unwrap();
+    *link = max.left_mut().take();
+    return max;
+}
+
+///
+/// Rotates the subtree so that its left child becomes the root.
+///
+fn rotate_right<N: Balanced>(link: &mut Option<Box<N>>) {
+    let mut root = link.take().expect("rotating an empty subtree");
+    let mut pivot = root.left_mut().take().expect("rotating right without a left child");
+    *root.left_mut() = pivot.right_mut().take();
+    root.update();
+    *pivot.right_mut() = Some(root);
+    pivot.update();
+    *link = Some(pivot);
+}
+
+///
+/// Rotates the subtree so that its right child becomes the root.
+///
+fn rotate_left<N: Balanced>(link: &mut Option<Box<N>>) {
+    let mut root = link.take().expect("rotating an empty subtree");
+    let mut pivot = root.right_mut().take().expect("rotating left without a right child");
+    *root.right_mut() = pivot.left_mut().take();
+    root.update();
+    *pivot.left_mut() = Some(root);
+    pivot.update();
+    *link = Some(pivot);
+}
+
+///
+/// Restores the AVL invariant at the root of the subtree, where the heights
+/// of the two children may differ by at most one, assuming it already holds
+/// for both children.
+///
+pub(crate) fn rebalance<N: Balanced>(link: &mut Option<Box<N>>) {
+    let factor = match *link {
+        Some(ref mut node) => {
+            node.update();
+            height(node.left()) as isize - height(node.right()) as isize
+        },
+        None => return,
+    };
+
+    let node = link.as_mut().unwrap();
+    if factor > 1 {
+        if balance_factor(node.left()) < 0 {
+            rotate_left(node.left_mut());
+        }
+        rotate_right(link);
+    } else if factor < -1 {
+        if balance_factor(node.right()) > 0 {
+            rotate_right(node.right_mut());
+        }
+        rotate_left(link);
+    }
+}
+
+///
+/// An ordered map, kept balanced as an AVL tree so that lookups and inserts
+/// take O(log n) regardless of the order the keys arrive in.
+///
+#[derive(Clone)]
+pub struct Tree<K: Ord, V> {
+    root: Link<K, V>,
+    size: usize,
+}
+
+#[derive(Clone)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    height: usize,
+    // number of nodes in the subtree rooted here, including this one
+    size: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V) -> Node<K, V> {
+        return Node { key: key, value: value, height: 1, size: 1, left: None, right: None };
+    }
+
+    fn entry(&self) -> (&K, &V) {
+        return (&self.key, &self.value);
+    }
+
+}
+
+impl<K, V> Balanced for Node<K, V> {
+    fn left(&self) -> &Link<K, V> {
+        return &self.left;
+    }
+
+    fn right(&self) -> &Link<K, V> {
+        return &self.right;
+    }
+
+    fn left_mut(&mut self) -> &mut Link<K, V> {
+        return &mut self.left;
+    }
+
+    fn right_mut(&mut self) -> &mut Link<K, V> {
+        return &mut self.right;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    /// Recomputes the cached height and size after either child has changed.
+    fn update(&mut self) {
+        self.height = 1 + max(height(&self.left), height(&self.right));
+        self.size = 1 + subtree_size(&self.left) + subtree_size(&self.right);
+    }
+}
+
+
+///
+/// A view into a single key of a `Tree`, which may or may not be present.
+///
+pub enum Entry<'a, K: Ord + 'a, V: 'a> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+
+    /// The key of the entry.
+    pub fn key(&self) -> &K {
+        return match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        };
+    }
+
+    ///
+    /// Inserts `default` if the key is not present, and returns the value.
+    ///
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        return self.or_insert_with(|| default);
+    }
+
+    ///
+    /// Inserts the result of `default` if the key is not present, and returns
+    /// the value.
+    ///
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        return match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        };
+    }
+
+    ///
+    /// Inserts the default value if the key is not present, and returns the value.
+    ///
+    pub fn or_default(self) -> &'a mut V where V: Default {
+        return self.or_insert_with(V::default);
+    }
+
+    ///
+    /// Runs `f` on the value if the key is present.
+    ///
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Entry<'a, K, V> {
+        return match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            },
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        };
+    }
+}
+
+///
+/// An entry for a key that is in the tree.
+///
+pub struct OccupiedEntry<'a, K: 'a, V: 'a> {
+    key: &'a K,
+    value: &'a mut V,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+
+    /// The key of the entry.
+    pub fn key(&self) -> &K {
+        return self.key;
+    }
+
+    /// The value of the entry.
+    pub fn get(&self) -> &V {
+        return self.value;
+    }
+
+    /// The value of the entry, mutably.
+    pub fn get_mut(&mut self) -> &mut V {
+        return self.value;
+    }
+
+    /// The value of the entry, borrowed for as long as the tree is.
+    pub fn into_mut(self) -> &'a mut V {
+        return self.value;
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        return ::std::mem::replace(self.value, value);
+    }
+}
+
+///
+/// An entry for a key that is not in the tree.
+///
+pub struct VacantEntry<'a, K: Ord + 'a, V: 'a> {
+    tree: &'a mut Tree<K, V>,
+    key: K,
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+
+    /// The key of the entry.
+    pub fn key(&self) -> &K {
+        return &self.key;
+    }
+
+    /// Takes back the key without inserting anything.
+    pub fn into_key(self) -> K {
+        return self.key;
+    }
+
+    ///
+    /// Inserts the value for the key, and returns it.
+    ///
+    pub fn insert(self, value: V) -> &'a mut V {
+        // the key is moved into the tree, so the new node is found again by
+        // its place in key order, which the insertion does not change
+        let rank = self.tree.rank(&self.key);
+        Tree::insert_node(&mut self.tree.root, self.key, value);
+        self.tree.size += 1;
+        return &mut self.tree.select_node_mut(rank).unwrap().value;
+    }
+}
+
+///
+/// A position in a `Tree` that can be moved to neighbouring keys. Moving past
+/// either end leaves the cursor on no entry, and moving again from there wraps
+/// around to the opposite end.
+///
+pub struct Cursor<'a, K: Ord + 'a, V: 'a> {
+    tree: &'a Tree<K, V>,
+    current: Option<&'a Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Cursor<'a, K, V> {
+
+    /// The entry the cursor is positioned at.
+    pub fn entry(&self) -> Option<(&'a K, &'a V)> {
+        return self.current.map(Node::entry);
+    }
+
+    /// The key the cursor is positioned at.
+    pub fn key(&self) -> Option<&'a K> {
+        return self.current.map(|node| &node.key);
+    }
+
+    /// The value the cursor is positioned at.
+    pub fn value(&self) -> Option<&'a V> {
+        return self.current.map(|node| &node.value);
+    }
+
+    ///
+    /// Moves to the next larger key and returns its entry.
+    ///
+    pub fn move_next(&mut self) -> Option<(&'a K, &'a V)> {
+        self.current = match self.current {
+            Some(node) => self.tree.ceiling_node(&node.key, false),
+            None => self.tree.first_node(),
+        };
+        return self.entry();
+    }
+
+    ///
+    /// Moves to the next smaller key and returns its entry.
+    ///
+    pub fn move_prev(&mut self) -> Option<(&'a K, &'a V)> {
+        self.current = match self.current {
+            Some(node) => self.tree.floor_node(&node.key, false),
+            None => self.tree.last_node(),
+        };
+        return self.entry();
+    }
+}
+
+///
+/// Iterator over the entries of a `Tree` in ascending key order.
+///
+pub struct Iter<'a, K: 'a, V: 'a> {
+    // nodes whose left subtree has been visited, smallest key on top
+    stack: Vec<&'a Node<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut link: &'a Link<K, V>) {
+        while let Some(ref node) = *link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = match self.stack.pop() {
+            Some(node) => node,
+            None => return None,
+        };
+        self.push_left(&node.right);
+        self.remaining -= 1;
+        return Some((&node.key, &node.value));
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (self.remaining, Some(self.remaining));
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+///
+/// Iterator over the entries of a `Tree` in ascending key order, with
+/// mutable access to the values.
+///
+pub struct IterMut<'a, K: 'a, V: 'a> {
+    // the parts of each node still to be visited, smallest key on top
+    stack: Vec<(&'a K, &'a mut V, &'a mut Link<K, V>)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut link: &'a mut Link<K, V>) {
+        while let Some(ref mut node) = *link {
+            let Node { ref key, ref mut value, ref mut left, ref mut right, .. } = **node;
+            self.stack.push((key, value, right));
+            link = left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let (key, value, right) = match self.stack.pop() {
+            Some(entry) => entry,
+            None => return None,
+        };
+        self.push_left(right);
+        self.remaining -= 1;
+        return Some((key, value));
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (self.remaining, Some(self.remaining));
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+///
+/// Iterator that consumes a `Tree`, yielding its entries in ascending key order.
+///
+pub struct IntoIter<K, V> {
+    stack: Vec<Box<Node<K, V>>>,
+    remaining: usize,
+}
+
+impl<K, V> IntoIter<K, V> {
+    fn push_left(&mut self, mut link: Link<K, V>) {
+        while let Some(mut node) = link {
+            link = node.left.take();
+            self.stack.push(node);
+        }
     }
+}
 
-    ///
-    /// Returns the key-value pair where the key is the largest value less than or
-    /// equal to the input key. If there is no key less than the input key, then
-    /// None is returned.
-    ///
-    /// ```
-    /// use boyer_moore::search::tree;
-    /// let mut tree: tree::Tree<i32, String> = tree::Tree::new();
-    /// tree.insert(5, "five".to_string());
-    /// tree.insert(4, "four".to_string());
-    /// tree.insert(3, "three".to_string());
-    /// tree.insert(8, "eight".to_string());
-    ///
-    /// assert_eq!(Some((&8, &"eight".to_string())), tree.lower_bound(10));
-    /// assert_eq!(None, tree.lower_bound(2));
-    /// ```
-    ///
-    pub fn lower_bound(&self, key: K) -> Option<(&K, &V)> {
-        return Tree::find_lower(&self.root, key);
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let mut node = match self.stack.pop() {
+            Some(node) => node,
+            None => return None,
+        };
+        self.push_left(node.right.take());
+        self.remaining -= 1;
+        let node = *node;
+        return Some((node.key, node.value));
     }
 
-    fn find_lower(node: &Option<Box<Node<K, V>>>, key: K) -> Option<(&K, &V)> {
-        match *node {
-            None => None,
-            Some(ref n) =>
-                match key.cmp(&n.key) {
-                    Ordering::Less => return Tree::find_lower(&n.left, key),
-                    Ordering::Greater => {
-                        let r = Tree::find_lower(&n.right, key);
-                        if r.is_none() {
-                            return Some((&n.key, &n.value));
-                        } else {
-                            return r;
-                        }                    
-                    },
-                    Ordering::Equal => return Some((&n.key, &n.value)),
-                },
-        }            
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (self.remaining, Some(self.remaining));
     }
+}
 
-    fn get_value(node: &Option<Box<Node<K, V>>>, key: K) -> Option<&V> {
-        match *node {
-            None => None,
-            Some(ref n) =>
-                match key.cmp(&n.key) {
-                    Ordering::Less => return Tree::get_value(&n.left, key),
-                    Ordering::Greater => return Tree::get_value(&n.right, key),
-                    Ordering::Equal => return Some(&n.value)
-                },
-        }
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord, V> IntoIterator for Tree<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(mut self) -> IntoIter<K, V> {
+        let mut iter = IntoIter { stack: Vec::new(), remaining: self.size };
+        iter.push_left(self.root.take());
+        return iter;
     }
+}
 
-    fn insert_node(node: &mut Option<Box<Node<K, V>>>, key: K, value: V) -> bool {
-        match *node {
-            None => {
-                *node = Some(Box::new(Node { key: key, value: value, left: None, right: None }));
-                return false;
-            },
-            Some(ref mut node) => {
-                match key.cmp(&node.key) {
-                    Ordering::Less => {
-                        return Tree::insert_node(&mut node.left, key, value);
-                    },
-                    Ordering::Greater => {
-                        return Tree::insert_node(&mut node.right, key, value);
-                    },
-                    Ordering::Equal => {
-                        node.key = key;
-                        node.value = value;
-                        return true;
-                    },
-                }
-            },
-        }
+///
+/// Iterator over the keys of a `Tree` in ascending order.
+///
+pub struct Keys<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        return self.inner.next().map(|(key, _)| key);
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.inner.size_hint();
     }
 }
 
-#[derive(Clone)]
-pub struct Tree<K: Ord, V> {
-    root: Option<Box<Node<K, V>>>,
-    size: usize,
+///
+/// Iterator over the values of a `Tree` in ascending order of their keys.
+///
+pub struct Values<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
 }
 
-#[derive(Clone)]
-struct Node<K, V> {
-    key: K,
-    value: V,
-    left: Option<Box<Node<K, V>>>,
-    right: Option<Box<Node<K, V>>>,
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        return self.inner.next().map(|(_, value)| value);
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.inner.size_hint();
+    }
 }
 
+///
+/// Iterator over the entries of a `Tree` whose keys fall within a range.
+///
+pub struct Range<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+    // the largest key in the range, after which iteration stops
+    last: Option<&'a K>,
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let last = match self.last {
+            Some(last) => last,
+            None => return None,
+        };
+        match self.inner.next() {
+            Some((key, value)) if key <= last => {
+                if key == last {
+                    self.last = None;
+                }
+                return Some((key, value));
+            },
+            _ => {
+                self.last = None;
+                return None;
+            },
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
+    use search::test_util::Random;
 
     #[test]
     fn empty_size() {
@@ -138,7 +1245,7 @@ mod tests {
     #[test]
     fn get_empty_tree() {
         let tree: Tree<String, String> = Tree::new();
-        assert_eq!(None, tree.get("key".to_string()), "nothing should be in the tree");
+        assert_eq!(None, tree.get(&"key".to_string()), "nothing should be in the tree");
     }
 
     #[test]
@@ -159,7 +1266,7 @@ mod tests {
         assert_eq!(size as usize, tree.size(), "large size");
 
         for i in 0..size {
-            assert_eq!(Some(&i), tree.get(i), "correct value");
+            assert_eq!(Some(&i), tree.get(&i), "correct value");
         }
     }
 
@@ -180,7 +1287,7 @@ mod tests {
         let value = "one".to_string();
         tree.insert(1, value);
 
-        assert_eq!(Some(&"one".to_string()), tree.get(1), "should return inserted value");
+        assert_eq!(Some(&"one".to_string()), tree.get(&1), "should return inserted value");
     }
 
     #[test]
@@ -191,7 +1298,7 @@ mod tests {
         tree.insert(5, 5);
         tree.insert(10, 10);
 
-        let result = tree.lower_bound(5);
+        let result = tree.lower_bound(&5);
         assert_eq!(Some((&5, &5)), result);
     }
 
@@ -203,7 +1310,7 @@ mod tests {
         tree.insert(5, 5);
         tree.insert(10, 10);
 
-        assert_eq!(Some((&5, &5)), tree.lower_bound(6));
+        assert_eq!(Some((&5, &5)), tree.lower_bound(&6));
     }
 
     #[test]
@@ -214,7 +1321,7 @@ mod tests {
         tree.insert(1, 1);
         tree.insert(10, 10);
 
-        assert_eq!(Some((&1, &1)), tree.lower_bound(2));
+        assert_eq!(Some((&1, &1)), tree.lower_bound(&2));
     }
 
     #[test]
@@ -225,7 +1332,7 @@ mod tests {
         tree.insert(5, 5);
         tree.insert(1, 1);
 
-        assert_eq!(Some((&1, &1)), tree.lower_bound(2));
+        assert_eq!(Some((&1, &1)), tree.lower_bound(&2));
     }
 
     #[test]
@@ -234,7 +1341,524 @@ mod tests {
         tree.insert(5, 5);
         tree.insert(1, 1);
         tree.insert(10, 10);
-        assert_eq!(None, tree.lower_bound(0));
+        assert_eq!(None, tree.lower_bound(&0));
+    }
+
+    /// Checks the AVL invariant and cached heights, returning the height.
+    fn check_balanced<K: Ord, V>(link: &Link<K, V>) -> usize {
+        match *link {
+            None => 0,
+            Some(ref node) => {
+                let left = check_balanced(&node.left);
+                let right = check_balanced(&node.right);
+                assert!(left <= right + 1 && right <= left + 1, "subtree out of balance");
+                assert_eq!(1 + max(left, right), node.height, "cached height");
+                assert_eq!(1 + subtree_size(&node.left) + subtree_size(&node.right), node.size,
+                           "cached size");
+                if let Some(ref l) = node.left {
+                    assert!(l.key < node.key, "left key ordered");
+                }
+                if let Some(ref r) = node.right {
+                    assert!(r.key > node.key, "right key ordered");
+                }
+                return node.height;
+            },
+        }
+    }
+
+    #[test]
+    fn sorted_inserts_stay_balanced() {
+        let mut tree: Tree<usize, usize> = Tree::new();
+        for i in 0..1000 {
+            tree.insert(i, i);
+        }
+        let height = check_balanced(&tree.root);
+        assert!(height <= 15, "height {} is logarithmic", height);
+    }
+
+    #[test]
+    fn descending_and_alternating_inserts_stay_balanced() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in 0..500 {
+            tree.insert(-i, i);
+            tree.insert(i + 1000, i);
+            tree.insert(if i % 2 == 0 { 500 - i } else { 500 + i }, i);
+        }
+        check_balanced(&tree.root);
+        assert_eq!(Some(&0), tree.get(&0), "value kept through rotations");
+    }
+
+    #[test]
+    fn million_sorted_keys() {
+        let size = 1_000_000;
+        let mut tree: Tree<usize, usize> = Tree::new();
+        for i in 0..size {
+            tree.insert(i * 2, i);
+        }
+        assert_eq!(size, tree.size());
+        assert_eq!(Some(&(size / 2)), tree.get(&size), "middle key");
+        assert_eq!(Some((&(size * 2 - 2), &(size - 1))), tree.lower_bound(&(size * 3)));
+        assert_eq!(Some((&10, &5)), tree.lower_bound(&11));
+        assert!(tree.root.as_ref().unwrap().height <= 29, "height is logarithmic");
+    }
+
+    #[test]
+    fn remove_missing() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        assert_eq!(None, tree.remove(&1), "empty tree");
+        tree.insert(2, 2);
+        assert_eq!(None, tree.remove(&1), "absent key");
+        assert_eq!(1, tree.size(), "size unchanged");
+    }
+
+    #[test]
+    fn remove_node_with_two_children() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in &[50, 25, 75, 12, 37, 60, 100] {
+            tree.insert(*i, *i);
+        }
+        assert_eq!(Some(50), tree.remove(&50));
+        assert_eq!(None, tree.get(&50));
+        assert_eq!(Some((&37, &37)), tree.lower_bound(&55), "neighbours kept");
+        assert_eq!(Some(&60), tree.get(&60));
+        check_balanced(&tree.root);
+    }
+
+    #[test]
+    fn pop_first_and_last() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        assert_eq!(None, tree.pop_first());
+        assert_eq!(None, tree.pop_last());
+        for i in 0..100 {
+            tree.insert(i, i * 10);
+        }
+        assert_eq!(Some((0, 0)), tree.pop_first());
+        assert_eq!(Some((99, 990)), tree.pop_last());
+        assert_eq!(98, tree.size());
+        check_balanced(&tree.root);
+
+        while tree.pop_first().is_some() {
+            check_balanced(&tree.root);
+        }
+        assert_eq!(0, tree.size(), "drained");
+    }
+
+    #[test]
+    fn clear() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+        tree.clear();
+        assert_eq!(0, tree.size());
+        assert_eq!(None, tree.get(&5));
+    }
+
+    #[test]
+    fn random_operations_match_btree_map() {
+        let mut random = Random::new(17);
+        let mut tree: Tree<u32, u32> = Tree::new();
+        let mut expected: BTreeMap<u32, u32> = BTreeMap::new();
+
+        for step in 0..20000 {
+            let key = random.next(500) as u32;
+            match random.next(5) {
+                0 | 1 => {
+                    let replaced = tree.insert(key, step);
+                    assert_eq!(expected.insert(key, step).is_some(), replaced, "insert {}", key);
+                },
+                2 | 3 => assert_eq!(expected.remove(&key), tree.remove(&key), "remove {}", key),
+                _ => {
+                    let first = expected.keys().next().cloned();
+                    let popped = tree.pop_first();
+                    assert_eq!(first.map(|k| (k, expected.remove(&k).unwrap())), popped);
+                    let last = expected.keys().next_back().cloned();
+                    let popped = tree.pop_last();
+                    assert_eq!(last.map(|k| (k, expected.remove(&k).unwrap())), popped);
+                },
+            }
+            assert_eq!(expected.len(), tree.size(), "size after step {}", step);
+            if step % 1000 == 0 {
+                check_balanced(&tree.root);
+            }
+        }
+
+        check_balanced(&tree.root);
+        for key in 0..500 {
+            assert_eq!(expected.get(&key), tree.get(&key), "value for {}", key);
+        }
+    }
+
+    #[test]
+    fn iterate_in_order() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in &[5, 3, 8, 1, 4, 7, 9, 2, 6] {
+            tree.insert(*i, *i * 10);
+        }
+        assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 9], tree.keys().cloned().collect::<Vec<i32>>());
+        assert_eq!(vec![10, 20, 30, 40, 50, 60, 70, 80, 90],
+                   tree.values().cloned().collect::<Vec<i32>>());
+        assert_eq!(9, tree.iter().len(), "exact size");
+        assert_eq!(0, Tree::<i32, i32>::new().iter().count(), "empty tree");
+    }
+
+    #[test]
+    fn iter_mut_updates_values() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+        for (key, value) in tree.iter_mut() {
+            *value = *key * 2;
+        }
+        for i in 0..100 {
+            assert_eq!(Some(&(i * 2)), tree.get(&i), "updated value");
+        }
+    }
+
+    #[test]
+    fn into_iter_consumes_in_order() {
+        let mut tree: Tree<i32, String> = Tree::new();
+        for i in (0..50).rev() {
+            tree.insert(i, i.to_string());
+        }
+        let entries: Vec<(i32, String)> = tree.into_iter().collect();
+        let expected: Vec<(i32, String)> = (0..50).map(|i| (i, i.to_string())).collect();
+        assert_eq!(expected, entries);
+    }
+
+    #[test]
+    fn iterate_large_tree() {
+        let mut tree: Tree<usize, usize> = Tree::new();
+        for i in 0..200_000 {
+            tree.insert(i, i);
+        }
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..200_000), "every key in order");
+        assert_eq!(200_000, tree.into_iter().count());
+    }
+
+    #[test]
+    fn range_matches_btree_map() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        let mut expected: BTreeMap<i32, i32> = BTreeMap::new();
+        for i in 0..40 {
+            tree.insert(i * 3, i);
+            expected.insert(i * 3, i);
+        }
+
+        for start in -2..125 {
+            for end in start..125 {
+                assert!(tree.range(start..end).eq(expected.range(start..end)),
+                        "{}..{}", start, end);
+                assert!(tree.range(start..=end).eq(expected.range(start..=end)),
+                        "{}..={}", start, end);
+            }
+            assert!(tree.range(start..).eq(expected.range(start..)), "{}..", start);
+            assert!(tree.range(..start).eq(expected.range(..start)), "..{}", start);
+        }
+        assert_eq!(40, tree.range(..).count(), "unbounded");
+        assert_eq!(0, tree.range((Bound::Excluded(6), Bound::Excluded(9))).count(), "gap");
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end in tree")]
+    fn inverted_range() {
+        let tree: Tree<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        let (start, end) = (50, 20);
+        tree.range(start..end);
+    }
+
+    #[test]
+    #[should_panic(expected = "range start and end are equal and excluded in tree")]
+    fn empty_excluded_range() {
+        let tree: Tree<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        tree.range((Bound::Excluded(5), Bound::Excluded(5)));
+    }
+
+    fn neighbour_tree() -> Tree<i32, i32> {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in &[50, 25, 12, 37, 75, 60, 100] {
+            tree.insert(*i, *i);
+        }
+        return tree;
+    }
+
+    #[test]
+    fn find_upper_exact_value() {
+        let tree = neighbour_tree();
+        assert_eq!(Some((&37, &37)), tree.upper_bound(&37));
+    }
+
+    #[test]
+    fn find_upper() {
+        let tree = neighbour_tree();
+        assert_eq!(Some((&60, &60)), tree.upper_bound(&51));
+        assert_eq!(Some((&12, &12)), tree.upper_bound(&0));
+        assert_eq!(None, tree.upper_bound(&101));
+    }
+
+    #[test]
+    fn successor_and_predecessor() {
+        let tree = neighbour_tree();
+        assert_eq!(Some((&60, &60)), tree.successor(&50), "strictly greater");
+        assert_eq!(Some((&37, &37)), tree.predecessor(&50), "strictly less");
+        assert_eq!(Some((&50, &50)), tree.successor(&40), "absent key");
+        assert_eq!(Some((&37, &37)), tree.predecessor(&40), "absent key");
+        assert_eq!(None, tree.successor(&100));
+        assert_eq!(None, tree.predecessor(&12));
+    }
+
+    #[test]
+    fn first_and_last() {
+        let tree = neighbour_tree();
+        assert_eq!(Some((&12, &12)), tree.first());
+        assert_eq!(Some((&100, &100)), tree.last());
+
+        let empty: Tree<i32, i32> = Tree::new();
+        assert_eq!(None, empty.first());
+        assert_eq!(None, empty.last());
+    }
+
+    #[test]
+    fn neighbours_match_btree_map() {
+        let mut random = Random::new(5);
+        let mut tree: Tree<u32, u32> = Tree::new();
+        let mut expected: BTreeMap<u32, u32> = BTreeMap::new();
+        for _ in 0..300 {
+            let key = random.next(1000) as u32;
+            tree.insert(key, key);
+            expected.insert(key, key);
+        }
+
+        for key in 0..1001 {
+            assert_eq!(expected.range(key..).next(), tree.upper_bound(&key), "ceiling {}", key);
+            assert_eq!(expected.range((key + 1)..).next(), tree.successor(&key),
+                       "successor {}", key);
+            assert_eq!(expected.range(..key).next_back(), tree.predecessor(&key),
+                       "predecessor {}", key);
+            assert_eq!(expected.range(..=key).next_back(), tree.lower_bound(&key),
+                       "floor {}", key);
+        }
+    }
+
+    #[test]
+    fn cursor_walks_both_ways() {
+        let tree = neighbour_tree();
+        assert!(tree.cursor(&51).is_none(), "absent key");
+
+        let mut cursor = tree.cursor_first();
+        let mut forward = vec![*cursor.key().unwrap()];
+        while let Some((key, _)) = cursor.move_next() {
+            forward.push(*key);
+        }
+        assert_eq!(vec![12, 25, 37, 50, 60, 75, 100], forward);
+
+        let mut cursor = tree.cursor_last();
+        let mut backward = vec![*cursor.key().unwrap()];
+        while let Some((key, _)) = cursor.move_prev() {
+            backward.push(*key);
+        }
+        assert_eq!(vec![100, 75, 60, 50, 37, 25, 12], backward);
+
+        let mut cursor = tree.cursor(&50).unwrap();
+        assert_eq!(Some(&50), cursor.value());
+        assert_eq!(Some((&37, &37)), cursor.move_prev());
+        assert_eq!(Some((&50, &50)), cursor.move_next());
+        assert_eq!(None, Tree::<i32, i32>::new().cursor_first().entry(), "empty tree");
+    }
+
+    #[test]
+    fn borrowed_string_lookups() {
+        let mut tree: Tree<String, usize> = Tree::new();
+        tree.insert("beta".to_string(), 2);
+        tree.insert("alpha".to_string(), 1);
+        assert_eq!(Some(&1), tree.get("alpha"), "lookup with &str");
+        assert!(tree.contains_key("beta"));
+        assert!(!tree.contains_key("gamma"));
+        assert_eq!(Some((&"alpha".to_string(), &1)), tree.lower_bound("b"));
+        assert_eq!(Some(2), tree.remove("beta"));
+        let bounds = (Bound::Included("a"), Bound::Excluded("b"));
+        assert_eq!(1, tree.range::<str, _>(bounds).count(), "range with &str bounds");
+    }
+
+    #[test]
+    fn get_mut_updates_value() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        tree.insert(1, 1);
+        *tree.get_mut(&1).unwrap() += 10;
+        assert_eq!(Some(&11), tree.get(&1));
+        assert_eq!(None, tree.get_mut(&2));
+    }
+
+    #[test]
+    fn entry_counts_matches() {
+        let mut counts: Tree<String, usize> = Tree::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(word.to_string()).or_insert(0) += 1;
+        }
+        assert_eq!(Some(&3), counts.get("the"));
+        assert_eq!(Some(&2), counts.get("and"));
+        assert_eq!(Some(&1), counts.get("bat"));
+        assert_eq!(5, counts.size(), "one key per distinct word");
+        check_balanced(&counts.root);
+    }
+
+    #[test]
+    fn entry_variants() {
+        let mut tree: Tree<i32, Vec<i32>> = Tree::new();
+        tree.entry(1).or_insert_with(Vec::new).push(1);
+        tree.entry(1).or_default().push(2);
+        tree.entry(2).and_modify(|v| v.push(99)).or_default();
+        assert_eq!(Some(&vec![1, 2]), tree.get(&1));
+        assert_eq!(Some(&vec![]), tree.get(&2), "and_modify skipped for vacant key");
+
+        match tree.entry(1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(&1, entry.key());
+                assert_eq!(vec![1, 2], entry.insert(vec![3]), "old value returned");
+            },
+            Entry::Vacant(_) => panic!("key 1 is present"),
+        }
+        match tree.entry(3) {
+            Entry::Vacant(entry) => assert_eq!(3, entry.into_key()),
+            Entry::Occupied(_) => panic!("key 3 is absent"),
+        }
+        assert_eq!(Some(&vec![3]), tree.get(&1));
+        assert_eq!(2, tree.size(), "into_key inserts nothing");
+    }
+
+    #[test]
+    fn entry_inserts_keep_balance() {
+        let mut tree: Tree<i32, i32> = Tree::new();
+        for i in 0..1000 {
+            *tree.entry(i % 250).or_insert(0) += 1;
+        }
+        assert_eq!(250, tree.size());
+        assert!(tree.values().all(|count| *count == 4), "each key counted four times");
+        check_balanced(&tree.root);
+    }
+
+    #[test]
+    fn rank_and_select_empty() {
+        let tree: Tree<i32, i32> = Tree::new();
+        assert_eq!(0, tree.rank(&5));
+        assert_eq!(None, tree.select(0));
+    }
+
+    #[test]
+    fn select_past_end() {
+        let tree = neighbour_tree();
+        assert_eq!(Some((&100, &100)), tree.select(6));
+        assert_eq!(None, tree.select(7));
+    }
+
+    #[test]
+    fn rank_and_select_match_sorted_vec() {
+        let mut random = Random::new(23);
+        let mut tree: Tree<u32, u32> = Tree::new();
+        let mut sorted: Vec<u32> = Vec::new();
+
+        for step in 0..5000 {
+            let key = random.next(400) as u32;
+            if random.next(3) == 0 {
+                tree.remove(&key);
+                if let Ok(index) = sorted.binary_search(&key) {
+                    sorted.remove(index);
+                }
+            } else {
+                tree.insert(key, key);
+                if let Err(index) = sorted.binary_search(&key) {
+                    sorted.insert(index, key);
+                }
+            }
+
+            if step % 250 == 0 {
+                check_balanced(&tree.root);
+                for probe in 0..401 {
+                    let expected = sorted.iter().filter(|k| **k < probe).count();
+                    assert_eq!(expected, tree.rank(&probe), "rank of {}", probe);
+                }
+                for (index, key) in sorted.iter().enumerate() {
+                    assert_eq!(Some((key, key)), tree.select(index), "select {}", index);
+                }
+                assert_eq!(None, tree.select(sorted.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn default_is_empty() {
+        let tree: Tree<i32, i32> = Default::default();
+        assert_eq!(0, tree.size());
+    }
+
+    #[test]
+    fn debug_prints_in_order() {
+        let tree: Tree<i32, &str> = vec![(2, "b"), (1, "a")].into_iter().collect();
+        assert_eq!("{1: \"a\", 2: \"b\"}", format!("{:?}", tree));
+    }
+
+    #[test]
+    fn equality_ignores_shape() {
+        let ascending: Tree<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let descending: Tree<i32, i32> = (0..100).rev().map(|i| (i, i)).collect();
+        assert_eq!(ascending, descending);
+
+        let mut different = descending.clone();
+        different.insert(50, -1);
+        assert!(ascending != different, "value differs");
+        different.insert(50, 50);
+        different.insert(100, 100);
+        assert!(ascending != different, "extra key");
+    }
+
+    #[test]
+    fn equal_trees_hash_equal() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of(tree: &Tree<i32, i32>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            tree.hash(&mut hasher);
+            return hasher.finish();
+        }
+
+        let ascending: Tree<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let descending: Tree<i32, i32> = (0..100).rev().map(|i| (i, i)).collect();
+        assert_eq!(hash_of(&ascending), hash_of(&descending));
+    }
+
+    #[test]
+    fn extend_replaces_existing() {
+        let mut tree: Tree<i32, i32> = vec![(1, 1), (2, 2)].into_iter().collect();
+        tree.extend(vec![(2, 20), (3, 30)]);
+        assert_eq!(vec![(1, 1), (2, 20), (3, 30)], tree.into_iter().collect::<Vec<(i32, i32)>>());
+    }
+
+    #[test]
+    fn borrowed_into_iterator() {
+        let mut tree: Tree<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        for (_, value) in &mut tree {
+            *value += 1;
+        }
+        let mut total = 0;
+        for (_, value) in &tree {
+            total += *value;
+        }
+        assert_eq!(55, total);
+    }
+
+    #[test]
+    fn index_by_key() {
+        let tree: Tree<String, usize> = vec![("a".to_string(), 1)].into_iter().collect();
+        assert_eq!(1, tree["a"]);
+        assert_eq!(1, tree[&"a".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found in tree")]
+    fn index_missing_key() {
+        let tree: Tree<i32, i32> = Tree::new();
+        let _ = tree[&1];
     }
 
     #[test]
@@ -248,8 +1872,8 @@ mod tests {
         tree.insert(60, 60);
         tree.insert(100, 100);
 
-        assert_eq!(Some((&50, &50)), tree.lower_bound(52));
-        assert_eq!(Some((&75, &75)), tree.lower_bound(99));
+        assert_eq!(Some((&50, &50)), tree.lower_bound(&52));
+        assert_eq!(Some((&75, &75)), tree.lower_bound(&99));
     }
         
 }
@@ -0,0 +1,376 @@
+//!
+//! Patterns with "don't care" positions, such as `ID-??-2024`, searched with
+//! a Horspool variant of Boyer-Moore.
+//!
+//! A wildcard stands for a single character, which takes from one to four
+//! bytes of UTF-8, so matches of the same pattern can differ in length. A
+//! byte that is not part of a valid UTF-8 character counts as a character of
+//! its own.
+//!
+//! ```
+//! use boyer_moore::search::wildcard::WildcardSearcher;
+//!
+//! let searcher = WildcardSearcher::new("ID-??-2024").unwrap();
+//! let ids: Vec<usize> = searcher.find_iter("ID-07-2024, ID-x-2024, ID-é1-2024").collect();
+//! assert_eq!(vec![0, 23], ids);
+//! ```
+
+use std::cmp::max;
+use std::cmp::min;
+use std::ops::Range;
+use std::str;
+
+use search::pattern::ESCAPE;
+use search::pattern::PatternError;
+use search::search::ALPHABET_SIZE;
+use search::search::MatchKind;
+
+/// The longest UTF-8 encoding of a character, in bytes.
+const MAX_CHAR_LEN: usize = 4;
+
+///
+/// A searcher for a pattern in which some positions match any character.
+///
+#[derive(Clone)]
+pub struct WildcardSearcher {
+    /// the byte at each position, or `None` where any character matches
+    pattern: Vec<Option<u8>>,
+    /// how far the pattern can move on when the byte under the last
+    /// position of its shortest match is the index
+    shift: [usize; ALPHABET_SIZE],
+    kind: MatchKind,
+}
+
+impl WildcardSearcher {
+
+    ///
+    /// Builds the searcher for a pattern where `?` matches any character, and
+    /// `\?` and `\\` stand for a literal `?` and `\`.
+    ///
+    pub fn new(pattern: &str) -> Result<WildcardSearcher, PatternError> {
+        return WildcardSearcher::with_wildcard(pattern, '?');
+    }
+
+    ///
+    /// Builds the searcher for a pattern where `wildcard` matches any
+    /// character. Any character preceded by a `\` is taken literally.
+    ///
+    /// ```
+    /// use boyer_moore::search::wildcard::WildcardSearcher;
+    ///
+    /// let searcher = WildcardSearcher::with_wildcard("v_._?", '_').unwrap();
+    /// assert_eq!(Some(4), searcher.find("see v1.2?"));
+    /// assert_eq!(None, searcher.find("see v1.2!"));
+    /// ```
+    ///
+    pub fn with_wildcard(pattern: &str, wildcard: char) -> Result<WildcardSearcher, PatternError> {
+        if wildcard == ESCAPE || !wildcard.is_ascii() {
+            return Err(PatternError::InvalidWildcard(wildcard));
+        }
+
+        let mut positions = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let literal = if c == ESCAPE {
+                chars.next().ok_or(PatternError::TrailingEscape)?
+            } else if c == wildcard {
+                positions.push(None);
+                continue;
+            } else {
+                c
+            };
+            let mut buffer = [0; 4];
+            positions.extend(literal.encode_utf8(&mut buffer).bytes().map(Some));
+        }
+        return Ok(WildcardSearcher::from_positions(positions));
+    }
+
+    ///
+    /// Builds the searcher from the byte each position must be, or `None`
+    /// for positions that match any character.
+    ///
+    pub fn from_positions(pattern: Vec<Option<u8>>) -> WildcardSearcher {
+        // the shifts are those of the shortest match, where every wildcard is
+        // a single byte, but each position may sit further into a longer
+        // match by the extra bytes of the wildcards before it, so a byte is
+        // given the shift of the furthest place it can take
+        let m = pattern.len();
+        let mut shift = [m; ALPHABET_SIZE];
+        let mut extra = 0;
+        for (i, position) in pattern.iter().enumerate() {
+            let furthest = i + extra + if position.is_none() { MAX_CHAR_LEN - 1 } else { 0 };
+            let distance = max(m.saturating_sub(1 + furthest), 1);
+            match *position {
+                Some(byte) => shift[byte as usize] = min(shift[byte as usize], distance),
+                // a wildcard lines up with every byte, bounding every shift
+                None => {
+                    for s in shift.iter_mut() {
+                        *s = min(*s, distance);
+                    }
+                    extra += MAX_CHAR_LEN - 1;
+                },
+            }
+        }
+        return WildcardSearcher { pattern: pattern, shift: shift, kind: MatchKind::NonOverlapping };
+    }
+
+    ///
+    /// Sets whether `find_iter` reports matches that overlap each other. The
+    /// default is `MatchKind::NonOverlapping`.
+    ///
+    pub fn match_kind(mut self, kind: MatchKind) -> WildcardSearcher {
+        self.kind = kind;
+        return self;
+    }
+
+    ///
+    /// The number of bytes the shortest match spans, where every wildcard
+    /// matches a single byte character.
+    ///
+    pub fn len(&self) -> usize {
+        return self.pattern.len();
+    }
+
+    /// Whether the pattern is empty, in which case it matches nowhere.
+    pub fn is_empty(&self) -> bool {
+        return self.pattern.is_empty();
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern.
+    ///
+    pub fn find<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H) -> Option<usize> {
+        return self.find_at(haystack.as_ref(), 0);
+    }
+
+    ///
+    /// Returns the offset of the first occurrence of the pattern that starts
+    /// at or after `start`.
+    /// # Arguments
+    /// * `haystack` - the body to search in
+    /// * `start` - the offset to begin searching from
+    ///
+    pub fn find_at(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        return self.find_span_at(haystack, start).map(|span| span.start);
+    }
+
+    ///
+    /// Returns the bytes spanned by the first occurrence of the pattern that
+    /// starts at or after `start`.
+    ///
+    /// ```
+    /// use boyer_moore::search::wildcard::WildcardSearcher;
+    ///
+    /// let searcher = WildcardSearcher::new("caf?").unwrap();
+    /// assert_eq!(Some(2..7), searcher.find_span_at("a café".as_bytes(), 0));
+    /// ```
+    ///
+    pub fn find_span_at(&self, haystack: &[u8], start: usize) -> Option<Range<usize>> {
+        let m = self.pattern.len();
+        if m == 0 || start > haystack.len() || haystack.len() - start < m {
+            return None;
+        }
+
+        // alignment of the end of the shortest match relative to the search
+        let mut k = start + m - 1;
+        while k < haystack.len() {
+            let offset = k + 1 - m;
+            if let Some(end) = self.match_end(haystack, offset) {
+                return Some(offset..end);
+            }
+            k += self.shift[haystack[k] as usize];
+        }
+        return None;
+    }
+
+    /// The end of the match that starts at the offset, if there is one.
+    fn match_end(&self, haystack: &[u8], offset: usize) -> Option<usize> {
+        let mut end = offset;
+        for position in &self.pattern {
+            match *position {
+                Some(byte) => {
+                    if haystack.get(end) != Some(&byte) {
+                        return None;
+                    }
+                    end += 1;
+                },
+                None => end += char_len(haystack, end)?,
+            }
+        }
+        return Some(end);
+    }
+
+    ///
+    /// Returns the offsets of every occurrence of the pattern, overlapping
+    /// each other or not depending on the match kind.
+    ///
+    pub fn find_iter<'s, 'h, H: AsRef<[u8]> + ?Sized>(&'s self, haystack: &'h H)
+                                                     -> WildcardFindIter<'s, 'h> {
+        return WildcardFindIter { searcher: self, haystack: haystack.as_ref(), position: 0 };
+    }
+}
+
+///
+/// Iterator over the offsets of the occurrences of a wildcard pattern.
+///
+pub struct WildcardFindIter<'s, 'h> {
+    searcher: &'s WildcardSearcher,
+    haystack: &'h [u8],
+    position: usize,
+}
+
+impl<'s, 'h> Iterator for WildcardFindIter<'s, 'h> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let found = self.searcher.find_span_at(self.haystack, self.position)?;
+        self.position = match self.searcher.kind {
+            MatchKind::Overlapping => found.start + 1,
+            MatchKind::NonOverlapping => found.end,
+        };
+        return Some(found.start);
+    }
+}
+
+///
+/// The length of the character that starts at the offset. A byte that is
+/// not part of a valid UTF-8 character counts as a character of its own,
+/// while a byte in the middle of one starts none.
+///
+fn char_len(haystack: &[u8], offset: usize) -> Option<usize> {
+    if offset >= haystack.len() {
+        return None;
+    }
+    if let Some(len) = valid_char_len(haystack, offset) {
+        return Some(len);
+    }
+    // the last byte before that is not a continuation byte starts the
+    // character, if any, that the offset is inside of
+    let start = offset.saturating_sub(MAX_CHAR_LEN - 1);
+    let lead = (start..offset).rev().find(|&i| haystack[i] & 0xC0 != 0x80);
+    return match lead.and_then(|lead| valid_char_len(haystack, lead).map(|len| lead + len)) {
+        Some(end) if end > offset => None,
+        _ => Some(1),
+    };
+}
+
+/// The length of the character at the offset, if the bytes there are valid UTF-8.
+fn valid_char_len(haystack: &[u8], offset: usize) -> Option<usize> {
+    let bytes = &haystack[offset..min(haystack.len(), offset + MAX_CHAR_LEN)];
+    let valid = match str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),
+    };
+    return valid.chars().next().map(char::len_utf8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::test_util::Random;
+
+    /// Every offset where the pattern matches, checked one character at a time.
+    fn naive(pattern: &str, haystack: &str) -> Vec<usize> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        return haystack.char_indices().map(|(i, _)| i).filter(|&i| {
+            let mut chars = haystack[i..].chars();
+            return pattern.chars().all(|p| chars.next().is_some_and(|c| p == '?' || p == c));
+        }).collect();
+    }
+
+    #[test]
+    fn escapes() {
+        let searcher = WildcardSearcher::new("a\\?b\\\\").unwrap();
+        assert_eq!(4, searcher.len());
+        assert_eq!(Some(4), searcher.find("acb a?b\\"));
+        assert_eq!(None, searcher.find("axb\\"));
+    }
+
+    #[test]
+    fn escaped_ordinary_character() {
+        let searcher = WildcardSearcher::new("\\a?").unwrap();
+        assert_eq!(Some(1), searcher.find("ba!"));
+    }
+
+    #[test]
+    fn trailing_escape() {
+        assert_eq!(Some(PatternError::TrailingEscape), WildcardSearcher::new("ab\\").err());
+    }
+
+    #[test]
+    fn invalid_wildcard() {
+        assert_eq!(Some(PatternError::InvalidWildcard('\\')),
+                   WildcardSearcher::with_wildcard("a", '\\').err());
+        assert_eq!(Some(PatternError::InvalidWildcard('é')),
+                   WildcardSearcher::with_wildcard("a", 'é').err());
+    }
+
+    #[test]
+    fn only_wildcards() {
+        let searcher = WildcardSearcher::new("??").unwrap();
+        assert_eq!(vec![0, 2], searcher.find_iter("abcde").collect::<Vec<usize>>());
+        let searcher = searcher.match_kind(MatchKind::Overlapping);
+        assert_eq!(vec![0, 1, 2, 3], searcher.find_iter("abcde").collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let searcher = WildcardSearcher::new("").unwrap();
+        assert!(searcher.is_empty());
+        assert_eq!(None, searcher.find("abc"));
+    }
+
+    #[test]
+    fn non_ascii_literals() {
+        let searcher = WildcardSearcher::new("é??").unwrap();
+        assert_eq!(4, searcher.len());
+        assert_eq!(Some(1), searcher.find("aébc"));
+    }
+
+    #[test]
+    fn wildcard_matches_a_whole_character() {
+        let searcher = WildcardSearcher::new("r?sum?").unwrap();
+        assert_eq!(Some(0..8), searcher.find_span_at("résumé".as_bytes(), 0));
+        assert_eq!(Some(0..6), searcher.find_span_at("resume".as_bytes(), 0));
+        let searcher = WildcardSearcher::new("?b").unwrap();
+        assert_eq!(vec![0, 4], searcher.find_iter("éb アb").collect::<Vec<usize>>());
+        assert_eq!(None, WildcardSearcher::new("a?c").unwrap().find("aébc"));
+    }
+
+    #[test]
+    fn wildcard_never_starts_inside_a_character() {
+        let searcher = WildcardSearcher::new("?").unwrap().match_kind(MatchKind::Overlapping);
+        assert_eq!(vec![0, 1, 3], searcher.find_iter("aéb").collect::<Vec<usize>>());
+        // bytes that are not UTF-8 are characters of their own
+        assert_eq!(vec![0, 1, 2], searcher.find_iter(&b"a\xff\x80"[..]).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn shift_allows_for_wide_characters() {
+        let searcher = WildcardSearcher::new("?ab").unwrap();
+        assert_eq!(Some(1), searcher.find("xéab"));
+        assert_eq!(Some(1), searcher.find("x\u{1F600}ab"));
+    }
+
+    #[test]
+    fn wildcard_bounds_the_shift() {
+        // without the bound the 'x' under the last position would skip the match
+        let searcher = WildcardSearcher::new("?bc").unwrap();
+        assert_eq!(Some(2), searcher.find("xxxbc"));
+    }
+
+    #[test]
+    fn matches_naive_search() {
+        let mut random = Random::new(43);
+        for _ in 0..2000 {
+            let haystack = random.string(&["a", "b", "c", "é", "ア", "\u{1F600}"], 30);
+            let pattern = random.string(&["a", "b", "é", "?"], 5);
+            let searcher = WildcardSearcher::new(&pattern).unwrap()
+                .match_kind(MatchKind::Overlapping);
+            assert_eq!(naive(&pattern, &haystack), searcher.find_iter(&haystack).collect::<Vec<_>>(),
+                       "{:?} in {:?}", pattern, haystack);
+        }
+    }
+}